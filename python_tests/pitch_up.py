@@ -1,16 +1,22 @@
 import json
-import numpy as np
+import math
 import sys
 
 def velocity_magnitude(components):
-    return np.power([k for k in components.values()], 2).sum() ** 0.5
+    return sum(k ** 2 for k in components.values()) ** 0.5
 
 def climb_angle(components):
-    return np.arctan(components['z']/horizontal_velocity(components))
+    return math.atan(components['z']/horizontal_velocity(components))
 
-def horizontal_velocity(components): 
+def horizontal_velocity(components):
     return velocity_magnitude({'x': components['x'], 'y': components['y']})
 
+def gradient(values):
+    # same as np.gradient, one sided at the ends and central in the middle
+    ends = [values[1] - values[0], values[-1] - values[-2]]
+    middle = [(values[i + 1] - values[i - 1]) / 2 for i in range(1, len(values) - 1)]
+    return [ends[0]] + middle + [ends[1]]
+
 states = json.load(open("pitch_up.json"))['data']
 positions = [s['position'] for s in states]
 pointings = [s['pointing_global'] for s in states]
@@ -18,7 +24,7 @@ velocities = [s['velocity'] for s in states]
 positions = [s['position'] for s in states]
 accelerations = [s['acceleration'] for s in states]
 
-if max(np.gradient([velocity_magnitude(v) for v in velocities])) < 0:
+if max(gradient([velocity_magnitude(v) for v in velocities])) < 0:
     sys.exit(0)
 else:
-    sys.exit(255)
\ No newline at end of file
+    sys.exit(255)
@@ -0,0 +1,279 @@
+use serde::Deserialize;
+
+use crate::common_math::Vec3;
+use crate::controls;
+use crate::definition::{AeroCoefficients, AircraftDefinition, Geometry};
+use crate::fin::Fin;
+use crate::flaps::FlapIncrements;
+use crate::wing::{GroundEffect, WingDefinition};
+
+const AUTOROTATION: f64 = 0.1; // nondimensional roll rate where the stalled wing stops feeding the roll, past it the down going side is too deep to help
+
+// everything an aero model can ask about the plane, in our units and with our signs
+pub struct AeroInputs {
+    pub density: f64, // kg/m^3
+    pub dynamic_pressure: f64, // Pa
+    pub airspeed: f64, // m/s
+    pub alpha: f64, // rad
+    pub beta: f64, // rad
+    pub angular_rate: Vec3, // body rates, ours
+    pub elevator: f64, // rad, positive nose up like the controls
+    pub aileron: f64,
+    pub rudder: f64,
+    pub flaps: f64, // degrees
+    pub stalled: bool,
+    pub panels_stalled: [bool; 2], // each half of the wing, left then right
+    pub height: f64, // m, of the wing above the ground
+}
+
+/*
+something that turns the airflow into forces. the wing and fin, the stability derivatives and the jsbsim
+tables all go through this, the aircraft keeps the stall hysteresis and hands it over in the inputs
+ */
+pub trait AeroModel {
+    // the force and the moment about the reference point, both in body coords
+    fn forces(&self, inputs: &AeroInputs) -> (Vec3, Vec3);
+}
+
+pub fn body_force(alpha: f64, beta: f64, drag: f64, side: f64, lift: f64) -> Vec3 {
+    // drag goes back along the airflow, lift is square to it, and the side force is to the right like the textbooks
+    let airflow = Vec3::new(alpha.cos() * beta.cos(), -beta.sin(), -alpha.sin() * beta.cos());
+    airflow * -drag + &Vec3::new(lift * alpha.sin(), -side, lift * alpha.cos())
+}
+
+/*
+the classic nondimensional derivatives, per radian with the rates made nondimensional by the span or
+chord over twice the airspeed. textbook axes (x forward, y right, z down) like the [aero] ones, but the
+controls keep our signs, so positive elevator is nose up, positive aileron rolls left and positive
+rudder yaws left. the flap ones are per degree
+ */
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct StabilityDerivatives {
+    pub clift_0: f64,
+    pub clift_alpha: f64,
+    pub clift_q: f64,
+    pub clift_elevator: f64,
+    pub clift_flaps: f64,
+    pub clift_max: f64, // theres no stall in here, the lift just stops growing
+    pub cdrag_0: f64,
+    pub cdrag_induced: f64, // times CL squared
+    pub cdrag_flaps: f64,
+    pub cside_beta: f64,
+    pub cside_rudder: f64,
+    pub croll_beta: f64,
+    pub croll_p: f64,
+    pub croll_r: f64,
+    pub croll_aileron: f64,
+    pub croll_rudder: f64,
+    pub cm_0: f64,
+    pub cm_alpha: f64,
+    pub cm_q: f64,
+    pub cm_elevator: f64,
+    pub cm_flaps: f64,
+    pub cyaw_beta: f64,
+    pub cyaw_p: f64,
+    pub cyaw_r: f64,
+    pub cyaw_aileron: f64,
+    pub cyaw_rudder: f64,
+}
+
+/*
+the derivatives with the wing they go with, and what a plane flies on if its definition has them.
+theyre straight lines round cruise, so theres no stall asymmetry or autorotation in here and it wont
+spin, that needs the wing and fin. the lift just tops out at clift_max
+ */
+#[derive(Clone)]
+pub struct DerivativeModel {
+    derivatives: StabilityDerivatives,
+    area: f64,
+    span: f64,
+    chord: f64,
+}
+
+impl DerivativeModel {
+    pub fn new(derivatives: &StabilityDerivatives, geometry: &Geometry) -> DerivativeModel {
+        DerivativeModel {
+            derivatives: derivatives.clone(),
+            area: geometry.area,
+            span: geometry.span,
+            chord: geometry.chord,
+        }
+    }
+}
+
+impl AeroModel for DerivativeModel {
+    #[allow(non_snake_case)]
+    fn forces(&self, inputs: &AeroInputs) -> (Vec3, Vec3) {
+        let d = &self.derivatives;
+        let qS = inputs.dynamic_pressure * self.area;
+        let (alpha, beta) = (inputs.alpha, inputs.beta);
+        // nondimensional rates, with pitch and yaw flipped to match the textbook axes
+        let rate = inputs.angular_rate;
+        let (p_hat, q_hat, r_hat) = if inputs.airspeed > 1.0 {
+            (
+                rate.x * self.span / (2.0 * inputs.airspeed),
+                -rate.y * self.chord / (2.0 * inputs.airspeed),
+                -rate.z * self.span / (2.0 * inputs.airspeed),
+            )
+        } else {
+            (0.0, 0.0, 0.0)
+        };
+
+        // the same ground effect as the wing, more lift and less induced drag near the runway
+        let ground = GroundEffect::at(inputs.height, self.span, self.area);
+        let CL = ((d.clift_0 + d.clift_alpha * alpha) * ground.lift + d.clift_q * q_hat + d.clift_elevator * inputs.elevator + d.clift_flaps * inputs.flaps).clamp(-d.clift_max, d.clift_max);
+        let CD = d.cdrag_0 + d.cdrag_induced * CL * CL * ground.induced_drag + d.cdrag_flaps * inputs.flaps;
+        let CY = d.cside_beta * beta + d.cside_rudder * inputs.rudder;
+        let Cl = d.croll_beta * beta + d.croll_p * p_hat + d.croll_r * r_hat + d.croll_aileron * inputs.aileron + d.croll_rudder * inputs.rudder;
+        let Cm = d.cm_0 + d.cm_alpha * alpha + d.cm_q * q_hat + d.cm_elevator * inputs.elevator + d.cm_flaps * inputs.flaps;
+        let Cn = d.cyaw_beta * beta + d.cyaw_p * p_hat + d.cyaw_r * r_hat + d.cyaw_aileron * inputs.aileron + d.cyaw_rudder * inputs.rudder;
+
+        let force = body_force(alpha, beta, CD * qS, CY * qS, CL * qS);
+        let moment = Vec3::new(Cl * qS * self.span, -Cm * qS * self.chord, -Cn * qS * self.span);
+        (force, moment)
+    }
+}
+
+/*
+the hand tuned model from before the derivatives, and what a plane without any flies on. the wing gives
+the lift and drag and each half of it makes its own once its stalled, which is what spins it, the fin does
+the sideslip and yaw damping, and the [aero] coefficients do the rest. its kept for the stall and spin
+ */
+#[derive(Clone)]
+pub struct WingAndFin {
+    wing: WingDefinition,
+    fin: Fin,
+    aero: AeroCoefficients,
+    area: f64,
+    span: f64,
+    chord: f64,
+}
+
+impl WingAndFin {
+    pub fn new(definition: &AircraftDefinition) -> WingAndFin {
+        WingAndFin {
+            wing: definition.wing.clone(),
+            fin: definition.fin.clone(),
+            aero: definition.aero.clone(),
+            area: definition.geometry.area,
+            span: definition.geometry.span,
+            chord: definition.geometry.chord,
+        }
+    }
+}
+
+impl AeroModel for WingAndFin {
+    #[allow(non_snake_case)]
+    fn forces(&self, inputs: &AeroInputs) -> (Vec3, Vec3) {
+        let aero = &self.aero;
+        let qS = inputs.dynamic_pressure * self.area;
+        let (alpha, beta) = (inputs.alpha, inputs.beta);
+        // the air going past, put back together from the angles for the fin and each half of the wing
+        let body_velocity = Vec3::new(alpha.cos() * beta.cos(), -beta.sin(), -alpha.sin() * beta.cos()) * inputs.airspeed;
+
+        let flap = FlapIncrements::at(inputs.flaps);
+        let ground = GroundEffect::at(inputs.height, self.span, self.area);
+        let CL = self.wing.lift(alpha, &flap, &ground, inputs.stalled) + aero.clift_elevator * inputs.elevator;
+        let CD = self.wing.drag(alpha, &flap, &ground, inputs.stalled);
+        let CY = aero.cside_beta * beta; // just the fuselage, the fin is on its own below
+
+        let rate = inputs.angular_rate;
+        let (fin_force, fin_moments) = self.fin.forces(&body_velocity, &rate, inputs.rudder, inputs.density);
+        let working = self.fin.working(alpha, controls::elevator_up(inputs.elevator));
+        let (fin_force, fin_moments) = (fin_force * working, fin_moments * working);
+
+        // nondimensional rates, with pitch and yaw flipped to match the textbook axes
+        let (p_hat, q_hat, r_hat) = if inputs.airspeed > 1.0 {
+            (
+                rate.x * self.span / (2.0 * inputs.airspeed),
+                -rate.y * self.chord / (2.0 * inputs.airspeed),
+                -rate.z * self.span / (2.0 * inputs.airspeed),
+            )
+        } else {
+            (0.0, 0.0, 0.0)
+        };
+        // past the stall the two halves of the wing do different things, more for the one going down
+        let (up, forwards) = self.wing.asymmetry(self.wing.panels(&body_velocity, &rate, self.span), &flap, inputs.panels_stalled);
+        let arm = self.wing.panel_arm(self.span);
+        let spin_moments = Vec3::new(2.0 * qS * arm * up, 0.0, -2.0 * qS * arm * forwards);
+
+        let autorotation = if inputs.stalled { (aero.croll_p_stalled - aero.croll_p) * p_hat.clamp(-AUTOROTATION, AUTOROTATION) } else { 0.0 };
+        let Cl = aero.croll_p * p_hat + autorotation + aero.croll_r * r_hat + aero.croll_beta * beta + aero.croll_aileron * inputs.aileron;
+        let Cm = aero.cm_0 + aero.cm_alpha * alpha + aero.cm_q * q_hat + aero.cm_elevator * inputs.elevator + flap.moment;
+        // the adverse yaw from rolling is an attached flow thing, stalled the down going wing just drags and thats in the spin moments
+        let Cn = aero.cyaw_r * r_hat + if inputs.stalled { 0.0 } else { aero.cyaw_p * p_hat } + aero.cyaw_aileron * inputs.aileron;
+
+        let force = body_force(alpha, beta, CD * qS, CY * qS, CL * qS) + &fin_force;
+        let moment = Vec3::new(Cl * qS * self.span, -Cm * qS * self.chord, -Cn * qS * self.span) + &fin_moments + &spin_moments;
+        (force, moment)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::common_math::deg_to_rad;
+
+    fn cessna() -> DerivativeModel {
+        let definition = AircraftDefinition::cessna();
+        DerivativeModel::new(definition.derivatives.as_ref().unwrap(), &definition.geometry)
+    }
+
+    fn cruising(alpha: f64, beta: f64) -> AeroInputs {
+        AeroInputs {
+            density: 1.225,
+            dynamic_pressure: 0.5 * 1.225 * 50.0_f64.powf(2.0),
+            airspeed: 50.0,
+            alpha,
+            beta,
+            angular_rate: Vec3::new(0.0, 0.0, 0.0),
+            elevator: 0.0,
+            aileron: 0.0,
+            rudder: 0.0,
+            flaps: 0.0,
+            stalled: false,
+            panels_stalled: [false; 2],
+            height: 1000.0,
+        }
+    }
+
+    #[test]
+    fn stable_every_way() {
+        let model = cessna();
+        // more alpha is more lift and pitches the nose back down
+        let (level, level_moment) = model.forces(&cruising(0.05, 0.0));
+        let (pitched, pitched_moment) = model.forces(&cruising(0.1, 0.0));
+        assert!(pitched.z > level.z);
+        assert!(pitched_moment.y > level_moment.y);
+        assert!(level.x < 0.0);
+        // wind from the right pushes it left, yaws the nose into it and rolls it away
+        let (force, moment) = model.forces(&cruising(0.05, 0.1));
+        assert!(force.y > 0.0);
+        assert!(moment.z < 0.0);
+        assert!(moment.x < 0.0);
+    }
+
+    #[test]
+    fn rates_are_damped() {
+        let model = cessna();
+        let mut inputs = cruising(0.05, 0.0);
+        let still = model.forces(&inputs).1;
+        inputs.angular_rate = Vec3::new(0.5, 0.5, 0.5);
+        let moving = model.forces(&inputs).1 - &still;
+        assert!(moving.x < 0.0 && moving.y < 0.0 && moving.z < 0.0);
+    }
+
+    #[test]
+    fn only_the_wing_knows_about_halves() {
+        // the left half letting go drops the left wing, the derivatives dont have halves so they dont care
+        let wing = WingAndFin::new(&AircraftDefinition::cessna());
+        let mut inputs = cruising(deg_to_rad(18.0), 0.0);
+        inputs.stalled = true;
+        inputs.angular_rate = Vec3::new(-0.5, 0.0, 0.0);
+        let (even, even_derivatives) = (wing.forces(&inputs).1, cessna().forces(&inputs).1);
+        inputs.panels_stalled = [true, false];
+        assert!(wing.forces(&inputs).1.x < even.x - 100.0);
+        assert_eq!(cessna().forces(&inputs).1.x, even_derivatives.x);
+    }
+}
@@ -1,209 +1,827 @@
-use crate::common_math::{Vec3, deg_to_rad, rad_to_deg};
-use crate::state;
-
-pub struct Aircraft {
-    pub state: state::State,
-    pub throttle_percent: f64,
-    mass: f64,
-    max_power: f64,
-    area: f64,
-}
-
-impl Aircraft {
-    pub fn new() -> Aircraft {
-        Aircraft {
-            state: state::State::runway(),
-            throttle_percent: 0.0,
-            mass: 1156.0,
-            max_power: 120e3,
-            area: 16.17,
-        }
-    }
-
-    pub fn flying() -> Aircraft {
-        Aircraft {
-            state: state::State::flying(),
-            throttle_percent: 0.7,
-            mass: 1000.0,
-            max_power: 120e3,
-            area: 16.17,
-        }
-    }
-    
-    pub fn flying_high() -> Aircraft {
-        Aircraft {
-            state: state::State::flying_high(),
-            throttle_percent: 0.7,
-            mass: 1000.0,
-            max_power: 120e3,
-            area: 16.17,
-        }
-    }
-
-    pub fn do_step(&mut self, dt: f64) {
-        // this also needs something for the combo roll and pitch to give yaw
-        let weight = self.mass * 9.81;
-        let weight_vector = Vec3::new(0.0, 0.0, -weight);
-
-        let self_forces = self.free_body_diagram().transform_coordinates(&self.state.pointing_global);
-        let mut next_acceleration = (self_forces + &weight_vector) * (1.0/self.mass);
-
-        let mut next_velocity = self.state.velocity + &(next_acceleration * dt);
-        let mut next_position = self.state.position + &(next_velocity * dt);
-        let next_pointing_global = self.state.pointing_global + &(self.state.angular_rate * dt);
-
-        if next_position.z <= 0.0 {
-            if self.state.velocity.z < -5. {
-                panic!("shit landing dumbass. {} meters per second", self.state.velocity.z);
-            }
-            if self.state.velocity.z < 0.0 {
-                println!("{} is acceptable", self.state.velocity.z);
-            }
-            next_position.z = self.state.position.z.max(0.0);
-            next_acceleration.z = self.state.acceleration.z.max(0.0);
-            next_velocity.z = self.state.velocity.z.max(0.0);
-        }
-        
-        self.state = state::State {
-            position: next_position,
-            velocity: next_velocity,
-            pointing_global: next_pointing_global,
-            acceleration: next_acceleration,
-            angular_rate: self.state.angular_rate,
-        }
-    }
-
-    #[allow(non_snake_case)]
-    fn free_body_diagram(&mut self) -> Vec3 {
-        let thrust = self.calculate_thrust();
-        let thrust_vectors = Vec3::new(thrust, 0.0, 0.0);
-
-        let alpha = self.get_alpha();
-        let CL = 1.2_f64.min(rad_to_deg(alpha)/10.0).max(-0.8);
-        let lift = 0.5 * 1.225 * self.state.velocity.magnitude().powf(2.0) * self.area * CL;
-        let lift_vectors = Vec3::new(0.0, 0.0, lift);
-
-        let CD = (alpha.powf(2.0)/(std::f64::consts::PI * 7.0)) + 0.05;
-        let drag = 0.5*1.225 * self.state.velocity.magnitude().powf(2.0) * self.area * CD;
-        let drag_vectors = Vec3::new(-drag * alpha.cos(), 0.0, drag * alpha.sin());
-        //let drag_vectors = Vec3::new(-drag, 0.0, 0.0);
-
-        let resultant = thrust_vectors + &drag_vectors + &lift_vectors;
-        resultant
-    }
-
-    pub fn calculate_thrust(&self) -> f64 {
-        let power = self.max_power * self.throttle_percent;
-        power / (self.state.velocity.magnitude())
-    }
-
-    pub fn throttle_by(&mut self, amount: f64) {
-        self.throttle_percent += amount;
-        if self.throttle_percent > 1.0 {
-            self.throttle_percent = 1.0;  
-        }
-        if self.throttle_percent < 0.0 {
-            self.throttle_percent = 0.0;  
-        }
-    }
-
-
-    pub fn pitch_by(&mut self, amount: f64) {
-        let delta_pitch = amount * deg_to_rad(self.state.pointing_global.roll).cos();
-        let delta_yaw = amount * deg_to_rad(self.state.pointing_global.roll).sin();
-
-        self.state.pointing_global.altitude += delta_pitch;
-        self.state.pointing_global.azimouth += delta_yaw;
-    }
-
-    pub fn yaw_by(&mut self, amount: f64 ){
-        let delta_pitch = amount * deg_to_rad(self.state.pointing_global.roll).sin();
-        let delta_yaw = amount * deg_to_rad(self.state.pointing_global.roll).cos();
-
-        self.state.pointing_global.altitude += delta_pitch;
-        self.state.pointing_global.azimouth += delta_yaw;
-    }
-
-    pub fn get_alpha(&self) -> f64 {
-        let climb_rate = self.state.velocity.angle_with_horizon();
-        deg_to_rad(self.state.pointing_global.altitude) - climb_rate 
-    }
-
-    pub fn get_sideslip(&self) -> f64 {
-        0.0
-    }
-
-}
-
-#[cfg(test)]
-mod test {
-    use state::State;
-    use crate::common_math::{rad_to_deg, Angles};
-
-    use super::*;
-    use std::io::Write;
-    use std::os::macos::raw::stat;
-    use std::process::Command;
-    
-    #[test]
-    fn pitching_up() {
-        // when pitching up, there should be a strictly decreasing velocity
-        let mut results_string = String::new();
-
-        let mut state = State::new();
-        state.position.z = 1e10;
-        state.velocity.x = 50.0;
-        let mut plane = Aircraft {
-            state,
-            throttle_percent: 0.0,
-            mass: 1156.0,
-            max_power: 120e3,
-            area: 16.17,
-        };
-        for a in 0..90 {
-
-            plane.state.pointing_global.altitude = a as f64;
-            plane.do_step(0.01);
-            results_string.push_str(plane.state.log().as_str());
-            results_string.push_str(",");
-        }
-    
-        std::fs::remove_file("pitch_up.json").unwrap();
-        let mut results_file = std::fs::OpenOptions::new().write(true).create(true).open("pitch_up.json").unwrap();
-        results_file.write("{\"data\": [".as_bytes()).unwrap();
-        results_string.pop();
-        results_string.push_str("]}");
-        results_file.write(results_string.as_bytes()).unwrap();
-
-        // run the python script and make sure its right
-        let mut command = Command::new("python");
-        command.arg("python_tests/pitch_up.py");
-        assert!(command.status().unwrap().success());
-    }
-
-    #[test]
-    fn test_alpha() {
-        let state = State {
-            pointing_global: Angles::new(0.0, 0.0, 0.0),
-            position: Vec3::new(0.0, 0.0, 0.0),
-            angular_rate: Angles::new(0.0, 0.0, 0.0),
-            velocity: Vec3::new(10.0, 0.0, 1.0),
-            acceleration: Vec3::new(0.0, 0.0, 0.0),
-        };
-        let mut plane = Aircraft {
-            state,
-            throttle_percent: 0.0,
-            mass: 10.0,
-            max_power: 1.0,
-            area: 1.0,
-        };
-        let target = -0.1_f64.atan();
-        let answer = plane.get_alpha();
-        assert!((target - answer).abs() < 1e-6);
-
-        plane.state.velocity = Vec3::new(0.0, 10.0, 1.0);
-        let target = -0.1_f64.atan();
-        let answer = plane.get_alpha();
-        assert!((target - answer).abs() < 1e-6);
-    }
+use crate::common_math::{Vec3, Mat3};
+use crate::balance::Balance;
+use crate::definition::{AircraftDefinition, MassProperties};
+use crate::integrator::{Integrator, StateDerivative};
+use crate::touchdown::{Contact, Monitor, Outcome};
+use crate::aero::{AeroInputs, AeroModel};
+use crate::{atmosphere, controls, engine, fin, flaps, fuel, gear, propeller, state, turbulence, wind, wing};
+
+pub struct Aircraft {
+    pub state: state::State,
+    pub throttle_percent: f64,
+    pub controls: controls::ControlSurfaces,
+    pub flaps: flaps::Flaps,
+    pub wing: wing::Wing,
+    pub gear: gear::Gear,
+    pub balance: Balance,
+    pub fuel: fuel::FuelSystem,
+    fin: fin::Fin,
+    propeller: propeller::Propeller,
+    pub propeller_effects: propeller::Effects,
+    pub engine: engine::Engine,
+    pub atmosphere: atmosphere::Atmosphere,
+    pub wind: wind::Wind,
+    pub turbulence: turbulence::Turbulence,
+    pub integrator: Integrator,
+    touchdown: Monitor,
+    contacts: Vec<Contact>, // what the touchdown monitor checks against the ground
+    pub aero_model: Box<dyn AeroModel>, // the stability derivatives if it has them, unless its been swapped for something else
+    empty: MassProperties,
+    mass: f64, // these three get worked out from the balance whenever the load changes
+    cg: Vec3, // from the reference point, in body coords
+    inertia: Mat3, // kg m^2 about the body axes through the CG
+    inverse_inertia: Mat3, // worked out with it so the integrator doesnt have to every stage
+    span: f64,
+    thrust_position: Vec3, // where the propeller is relative to the reference point
+    wing_position: Vec3,
+}
+
+impl Aircraft {
+    pub fn new() -> Aircraft {
+        Aircraft::from_definition(&AircraftDefinition::cessna())
+    }
+
+    pub fn from_definition(definition: &AircraftDefinition) -> Aircraft {
+        // sat on the runway with the engine idling
+        let mut aircraft = Aircraft {
+            state: state::State::runway(),
+            throttle_percent: 0.0,
+            controls: controls::ControlSurfaces::new(),
+            flaps: flaps::Flaps::new(),
+            wing: wing::Wing::new(definition.wing.clone()),
+            gear: definition.gear.clone(),
+            balance: definition.balance.clone(),
+            fuel: fuel::FuelSystem::new(),
+            fin: definition.fin.clone(),
+            propeller: definition.propeller.clone(),
+            propeller_effects: propeller::Effects::all(),
+            engine: engine::Engine::new(definition.engine.clone()),
+            atmosphere: atmosphere::Atmosphere::new(),
+            wind: wind::Wind::calm(),
+            turbulence: turbulence::Turbulence::new(1),
+            integrator: Integrator::SemiImplicitEuler,
+            touchdown: Monitor::new(),
+            contacts: definition.geometry.contacts.clone(),
+            aero_model: definition.aero_model(true),
+            empty: definition.mass.clone(),
+            mass: definition.mass.mass,
+            cg: Vec3::new(0.0, 0.0, 0.0),
+            inertia: Mat3::inertia(0.0, 0.0, 0.0, 0.0),
+            inverse_inertia: Mat3::inertia(0.0, 0.0, 0.0, 0.0),
+            span: definition.geometry.span,
+            thrust_position: definition.geometry.thrust_position,
+            wing_position: definition.geometry.wing_position,
+        };
+        aircraft.update_mass();
+        aircraft.state.position.z = aircraft.gear.rest_height(aircraft.mass * 9.81) + aircraft.cg.z;
+        aircraft
+    }
+
+    pub fn update_mass(&mut self) {
+        // call after changing the load, the fuel burn does it every step
+        let loading = self.balance.loaded(&self.empty);
+        let [ixx, iyy, izz, ixz] = loading.inertia;
+        self.mass = loading.mass;
+        self.cg = loading.cg;
+        self.inertia = Mat3::inertia(ixx, iyy, izz, ixz);
+        self.inverse_inertia = self.inertia.inverse().expect("the definition check makes sure the inertia inverts");
+    }
+
+    pub fn mass(&self) -> f64 {
+        self.mass
+    }
+
+    pub fn loading_problems(&self) -> Vec<String> {
+        self.balance.problems(&self.balance.loaded(&self.empty))
+    }
+
+    pub fn cg_arm(&self) -> f64 {
+        self.balance.arm(&self.cg)
+    }
+
+    fn reference_state(&self, state: &state::State) -> state::State {
+        // the state follows the CG, but the gear and everything else are measured from the reference point
+        let offset = Vec3::new(0.0, 0.0, 0.0) - &self.cg;
+        state::State {
+            position: state.position + &state.attitude.rotate(&offset),
+            velocity: state.velocity + &state.attitude.rotate(&state.angular_rate.cross_product(&offset)),
+            ..*state
+        }
+    }
+
+    pub fn airborne(mut self, state: state::State) -> Aircraft {
+        // already going somewhere, with the engine running at cruise
+        self.state = state;
+        self.throttle_percent = 0.7;
+        self.engine.rpm = 2300.0;
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn flying() -> Aircraft {
+        Aircraft::new().airborne(state::State::flying())
+    }
+    
+    #[allow(dead_code)]
+    pub fn flying_high() -> Aircraft {
+        Aircraft::new().airborne(state::State::flying_high())
+    }
+
+    pub fn do_step(&mut self, dt: f64) -> Option<Outcome> {
+        // the engine, flaps, stall, wind and turbulence only get looked at once a step, the rigid body goes through the integrator
+        self.turbulence.step(dt, self.state.airspeed(), self.state.position.z, self.span);
+        self.update_air();
+        let air = self.atmosphere.at(self.state.position.z);
+        let airspeed = self.state.body_air_velocity().x;
+        let propeller_power = self.propeller.power_required(airspeed, self.engine.rpm, air.density);
+        self.engine.step(dt, self.throttle_percent, &air, propeller_power);
+        self.engine.starved = !self.fuel.step(&mut self.balance, self.engine.fuel_flow, dt);
+        self.update_mass();
+        self.flaps.step(dt, self.equivalent_airspeed());
+        self.wing.update(self.get_alpha());
+        self.wing.update_panels(self.wing.panels(&self.state.body_air_velocity(), &self.state.air_rate(), self.span).map(|(alpha, _)| alpha));
+
+        let next = self.integrator.step(&self.state, dt, |state| self.derivative(state));
+
+        // the gear does the ground, this just watches how well you arrived
+        let (previous, next_reference) = (self.reference_state(&self.state), self.reference_state(&next));
+        let was_on_ground = self.gear.on_ground(&previous);
+        let on_ground = self.gear.on_ground(&next_reference);
+        let outcome = self.touchdown.check(&self.contacts, &previous, &next_reference, was_on_ground, on_ground, dt);
+
+        self.state = next;
+        self.update_air();
+        outcome
+    }
+
+    fn update_air(&mut self) {
+        // the steady wind for this height plus whatever the turbulence is doing, which is in body coords
+        let gusts = self.state.attitude.rotate(&self.turbulence.velocity);
+        self.state.wind = self.wind.at(self.state.position.z) + &gusts;
+        self.state.gust_rate = self.turbulence.rate;
+    }
+
+    pub fn derivative(&self, state: &state::State) -> StateDerivative {
+        let weight = self.mass * 9.81;
+        let weight_vector = Vec3::new(0.0, 0.0, -weight);
+
+        let (self_forces, self_moments) = self.free_body_diagram(state);
+        let self_forces = state.attitude.rotate(&self_forces);
+        let acceleration = (self_forces + &weight_vector) * (1.0/self.mass);
+
+        // eulers equations, I dw/dt = M - w x Iw, with the spinning prop adding to the angular momentum
+        let mut angular_momentum = self.inertia.mul_vec(&state.angular_rate);
+        if self.propeller_effects.gyroscopic {
+            angular_momentum = angular_momentum + &Vec3::new(self.engine.angular_momentum(), 0.0, 0.0);
+        }
+        let gyroscopic = state.angular_rate.cross_product(&angular_momentum);
+        let angular_acceleration = self.inverse_inertia.mul_vec(&(self_moments - &gyroscopic));
+
+        StateDerivative {
+            velocity: state.velocity,
+            acceleration,
+            attitude: state.attitude.derivative(&state.angular_rate),
+            angular_acceleration,
+        }
+    }
+
+    fn free_body_diagram(&self, state: &state::State) -> (Vec3, Vec3) {
+        // returns the forces and the moments about the CG, both in body coords
+        // everything gets worked out about the reference point and then moved to wherever the CG is
+        let density = self.atmosphere.at(state.position.z).density;
+        let thrust_vectors = Vec3::new(self.thrust_at(state), 0.0, 0.0);
+        let thrust_moments = self.thrust_position.cross_product(&thrust_vectors) + &self.propeller_moments(state, density, thrust_vectors.x);
+
+        let (aero_force, aero_moments) = self.aero_model.forces(&AeroInputs {
+            density,
+            dynamic_pressure: 0.5 * density * state.airspeed().powf(2.0),
+            airspeed: state.airspeed(),
+            alpha: state.alpha(),
+            beta: state.sideslip(),
+            angular_rate: state.air_rate(),
+            elevator: self.controls.elevator_angle(),
+            aileron: self.controls.aileron,
+            rudder: self.controls.rudder,
+            flaps: self.flaps.position,
+            stalled: self.wing.stalled,
+            panels_stalled: self.wing.panels_stalled,
+            height: self.wing_height(state),
+        });
+
+        let (gear_force, gear_moments) = self.gear.forces(&self.reference_state(state), self.controls.rudder);
+
+        let force = aero_force + &thrust_vectors + &gear_force;
+        let moments = aero_moments + &thrust_moments + &gear_moments - &self.cg.cross_product(&force);
+        (force, moments)
+    }
+
+    fn propeller_moments(&self, state: &state::State, density: f64, thrust: f64) -> Vec3 {
+        // the left turning tendencies apart from the gyroscopic one, which goes in with the rest of the spinning
+        let effects = &self.propeller_effects;
+        let airspeed = state.body_air_velocity().x;
+        let torque = self.propeller.torque(airspeed, self.engine.rpm, density);
+        let mut moments = Vec3::new(0.0, 0.0, 0.0);
+        if effects.torque {
+            moments.x -= torque;
+        }
+        if effects.p_factor {
+            let offset = self.propeller.thrust_offset(state.alpha(), state.sideslip());
+            moments = moments + &offset.cross_product(&Vec3::new(thrust, 0.0, 0.0));
+        }
+        if effects.slipstream {
+            // only the extra the wash adds, the fin on its own is already in the aero
+            let body_velocity = state.body_air_velocity();
+            let wash = self.propeller.slipstream(airspeed, thrust, torque, density);
+            let (_, with) = self.fin.forces(&(body_velocity + &wash), &state.air_rate(), self.controls.rudder, density);
+            let (_, without) = self.fin.forces(&body_velocity, &state.air_rate(), self.controls.rudder, density);
+            moments = moments + &(with - &without);
+        }
+        moments
+    }
+
+    fn wing_height(&self, state: &state::State) -> f64 {
+        // the ground is flat at z = 0
+        let reference = self.reference_state(state);
+        (reference.position + &reference.attitude.rotate(&self.wing_position)).z
+    }
+
+    #[allow(dead_code)]
+    pub fn calculate_thrust(&self) -> f64 {
+        self.thrust_at(&self.state)
+    }
+
+    fn thrust_at(&self, state: &state::State) -> f64 {
+        let density = self.atmosphere.at(state.position.z).density;
+        let airspeed = state.body_air_velocity().x;
+        self.propeller.thrust(airspeed, self.engine.rpm, density)
+    }
+
+    pub fn steady_throttle(&self) -> Result<f64, String> {
+        // the throttle that keeps the engine at the rpm its at, with the prop loaded like it is now
+        let air = self.atmosphere.at(self.state.position.z);
+        let propeller_power = self.propeller.power_required(self.state.body_air_velocity().x, self.engine.rpm, air.density);
+        self.engine.throttle_for(self.engine.rpm, &air, propeller_power)
+    }
+
+    pub fn throttle_by(&mut self, amount: f64) {
+        self.throttle_percent = (self.throttle_percent + amount).clamp(0.0, 1.0);
+    }
+
+
+    pub fn log(&self) -> String {
+        let alpha = self.get_alpha();
+        self.state.log_with(&[
+            ("stall_margin", self.wing.stall_margin(alpha).to_string()),
+            ("stall_warning", self.wing.is_stalling(alpha).to_string()),
+            ("mass", self.mass.to_string()),
+            ("cg_arm", self.cg_arm().to_string()),
+            ("fuel", self.fuel_json()),
+            ("fuel_selector", format!("\"{}\"", self.fuel.selector.name())),
+            ("fuel_flow", self.engine.fuel_flow.to_string()),
+            ("engine_starved", self.engine.starved.to_string()),
+            ("turbulence", format!("\"{}\"", self.turbulence.intensity.name())),
+            ("gust_rate", self.state.gust_rate.jsonify()),
+        ])
+    }
+
+    fn fuel_json(&self) -> String {
+        // whats left in each tank by name, in kg
+        let tanks: Vec<String> = self.balance.tanks.iter().map(|tank| format!("\"{}\": {}", tank.name, tank.fuel)).collect();
+        format!("{{{}}}", tanks.join(", "))
+    }
+
+    pub fn equivalent_airspeed(&self) -> f64 {
+        // what the airspeed indicator reads near enough, the true airspeed scaled down by how thin the air is
+        let density = self.atmosphere.at(self.state.position.z).density;
+        self.state.airspeed() * (density / atmosphere::sea_level_density()).sqrt()
+    }
+
+    pub fn get_alpha(&self) -> f64 {
+        self.state.alpha()
+    }
+
+    #[allow(dead_code)]
+    pub fn get_sideslip(&self) -> f64 {
+        self.state.sideslip()
+    }
+
+}
+
+#[cfg(test)]
+mod test {
+    use state::State;
+    use crate::common_math::{Angles, Quaternion};
+
+    use super::*;
+    use std::io::Write;
+    use std::process::Command;
+    
+    #[test]
+    fn pitching_up() {
+        // when pitching up, there should be a strictly decreasing velocity
+        let mut results_string = String::new();
+
+        let mut state = State::new();
+        state.position.z = 1000.0; // high enough to miss the ground, low enough that theres still air
+        state.velocity.x = 50.0;
+        let mut plane = Aircraft {
+            state,
+            throttle_percent: 0.0,
+            mass: 1156.0,
+            ..Aircraft::new()
+        };
+        for a in 0..90 {
+
+            plane.state.attitude = Quaternion::from_angles(&Angles::new(0.0, a as f64, 0.0));
+            plane.do_step(0.01);
+            results_string.push_str(plane.state.log().as_str());
+            results_string.push(',');
+        }
+    
+        let _ = std::fs::remove_file("pitch_up.json");
+        let mut results_file = std::fs::OpenOptions::new().write(true).create(true).truncate(true).open("pitch_up.json").unwrap();
+        results_file.write_all("{\"data\": [".as_bytes()).unwrap();
+        results_string.pop();
+        results_string.push_str("]}");
+        results_file.write_all(results_string.as_bytes()).unwrap();
+
+        // run the python script and make sure its right
+        let mut command = Command::new("python");
+        command.arg("python_tests/pitch_up.py");
+        assert!(command.status().unwrap().success());
+    }
+
+    #[test]
+    fn pitch_rate_damps_out() {
+        // kick the nose up and it should settle back down on its own
+        let mut plane = Aircraft::flying_high();
+        plane.state.angular_rate.y = -0.5;
+        // the prop would couple it into yaw and roll, this is just the pitch
+        plane.propeller_effects = propeller::Effects::none();
+        for _ in 0..200 {
+            plane.do_step(0.01);
+        }
+        assert!(plane.state.angular_rate.y.abs() < 0.05);
+        assert!(plane.state.angular_rate.x.abs() < 1e-10);
+        assert!(plane.state.angular_rate.z.abs() < 1e-10);
+    }
+
+    #[test]
+    fn elevator_needs_airspeed() {
+        // the same elevator should pitch the plane much harder when its going fast
+        let pitch_acceleration = |speed: f64| {
+            let mut plane = Aircraft::flying_high();
+            plane.state.velocity = Vec3::new(speed, 0.0, 0.0);
+            plane.throttle_percent = 0.0;
+            plane.controls.elevator_by(-10.0);
+            let before = plane.state.angular_rate.y;
+            plane.do_step(0.01);
+            plane.state.angular_rate.y - before
+        };
+        let slow = pitch_acceleration(10.0);
+        let fast = pitch_acceleration(50.0);
+        // down elevator is nose down, which is positive about body y
+        assert!(slow > 0.0);
+        assert!(fast > 10.0 * slow);
+    }
+
+    #[test]
+    fn weathercocks_into_sideslip() {
+        // flying along with some sideslip the nose should come round to the wind and the wing should drop away from it
+        let mut plane = Aircraft::flying_high();
+        plane.state.attitude = Quaternion::from_angles(&Angles::new(10.0, 6.0, 0.0));
+        for _ in 0..50 {
+            plane.do_step(0.01);
+        }
+        assert!(plane.state.angular_rate.z < 0.0);
+        assert!(plane.state.angular_rate.x < 0.0);
+    }
+
+    #[test]
+    fn less_lift_up_high() {
+        let climb_acceleration = |altitude: f64| {
+            let mut plane = Aircraft::flying_high();
+            plane.state.position.z = altitude;
+            plane.do_step(0.01);
+            plane.state.acceleration.z
+        };
+        assert!(climb_acceleration(0.0) > climb_acceleration(3000.0));
+    }
+
+    #[test]
+    fn flies_through_the_air_not_over_the_ground() {
+        // pointing north into a northerly, the wings see more air than the ground goes by
+        let climb_acceleration = |wind: wind::Wind| {
+            let mut plane = Aircraft::flying_high();
+            plane.wind = wind;
+            plane.do_step(0.01);
+            assert!(plane.state.airspeed() >= plane.state.groundspeed());
+            (plane.state.acceleration.z, plane.state.airspeed() - plane.state.groundspeed())
+        };
+        let (calm, _) = climb_acceleration(wind::Wind::calm());
+        let (headwind, difference) = climb_acceleration(wind::Wind::steady(0.0, 10.0));
+        assert!(headwind > calm);
+        assert!((difference - 10.0).abs() < 0.5);
+
+        // an easterly comes in from the right, the fin swings the nose round into it
+        let mut plane = Aircraft::flying_high();
+        plane.wind = wind::Wind::steady(90.0, 10.0);
+        plane.do_step(0.01);
+        assert!(plane.get_sideslip() > 0.1);
+        assert!(plane.derivative(&plane.state).angular_acceleration.z < 0.0);
+    }
+
+    #[test]
+    fn bumps_about_the_same_every_time() {
+        let fly = |seed: u64| {
+            let mut plane = Aircraft::flying_high();
+            plane.turbulence = turbulence::Turbulence::new(seed);
+            plane.turbulence.intensity = turbulence::Intensity::Severe;
+            (0..400).map(|_| {
+                plane.do_step(0.005);
+                plane.state.acceleration.z
+            }).collect::<Vec<f64>>()
+        };
+        assert_eq!(fly(3), fly(3));
+        assert_ne!(fly(3), fly(4));
+
+        // an updraft lifts it, and the air rolling round drags the wings with it
+        let mut plane = Aircraft::flying_high();
+        let calm = plane.derivative(&plane.state);
+        plane.turbulence.gust(Vec3::new(0.0, 0.0, 5.0), 40.0);
+        for _ in 0..100 {
+            plane.do_step(0.005);
+        }
+        assert!(plane.state.acceleration.z > calm.acceleration.z + 2.0);
+        let mut rolling_air = Aircraft::flying_high();
+        rolling_air.state.gust_rate = Vec3::new(0.5, 0.0, 0.0);
+        assert!(rolling_air.derivative(&rolling_air.state).angular_acceleration.x > calm.angular_acceleration.x + 1.0);
+    }
+
+    #[test]
+    fn floats_in_ground_effect() {
+        // the same flare a couple of metres up and well clear of the runway, down low it holds up better
+        let flare = |height: f64| {
+            let mut plane = Aircraft::flying_high();
+            plane.state.position.z = height;
+            plane.state.attitude = Quaternion::from_angles(&Angles::new(0.0, 8.0, 0.0));
+            plane.state.velocity = Vec3::new(28.0, 0.0, -1.0);
+            plane.throttle_percent = 0.0;
+            // not quite touching, so its all aero
+            assert!(!plane.gear.on_ground(&plane.reference_state(&plane.state)));
+            plane.derivative(&plane.state).acceleration
+        };
+        let (low, high) = (flare(1.5), flare(40.0));
+        assert!(low.z > high.z + 0.1);
+        assert!(low.x > high.x);
+    }
+
+    #[test]
+    fn takes_off_from_standstill() {
+        let mut plane = Aircraft::new();
+        plane.throttle_percent = 1.0;
+        // give the engine a second to spool up from idle
+        for _ in 0..100 {
+            plane.do_step(0.01);
+        }
+        assert!(plane.state.acceleration.x.is_finite());
+        // full power on a 172 is something like 0.2 g on the ground
+        assert!(plane.state.acceleration.x > 1.5 && plane.state.acceleration.x < 3.5);
+    }
+
+    #[test]
+    fn lands_and_brakes_to_a_stop() {
+        // a gentle arrival, the gear should soak it up and the brakes should stop it
+        let mut plane = Aircraft::new();
+        plane.state.position.z = 1.2;
+        plane.state.velocity = Vec3::new(28.0, 0.0, -1.5);
+        plane.state.attitude = Quaternion::from_angles(&Angles::new(0.0, 4.0, 0.0));
+        let mut landing = None;
+        for _ in 0..600 {
+            landing = landing.or(plane.do_step(0.005));
+        }
+        assert!(plane.gear.on_ground(&plane.state));
+        match landing {
+            Some(Outcome::Landed(report)) => {
+                assert!(report.sink_rate > 1.0 && report.sink_rate < 2.5);
+                assert!(report.g_load > 1.0 && report.g_load < 3.0);
+            },
+            _ => panic!("should have landed"),
+        }
+        plane.gear.left_brake = 1.0;
+        plane.gear.right_brake = 1.0;
+        for _ in 0..4000 {
+            plane.do_step(0.005);
+        }
+        assert!(plane.state.velocity.magnitude() < 0.5);
+        assert!(plane.state.pointing_global().roll.abs() < 1.0);
+        assert!(plane.state.position.z > 0.5);
+    }
+
+    #[test]
+    fn hard_landing_is_a_crash() {
+        let mut plane = Aircraft::new();
+        plane.state.position.z = 3.0;
+        plane.state.velocity = Vec3::new(30.0, 0.0, -7.0);
+        let mut outcome = None;
+        for _ in 0..200 {
+            outcome = outcome.or(plane.do_step(0.005));
+        }
+        match outcome {
+            Some(Outcome::Crashed(crash)) => {
+                assert_eq!(crash.cause, crate::touchdown::CrashCause::HardLanding);
+                assert!(crash.report.sink_rate > 5.0);
+            },
+            _ => panic!("should have crashed"),
+        }
+    }
+
+    #[test]
+    fn flies_whatever_its_given() {
+        // the same plane with twice the mass should come down faster
+        let mut heavy = AircraftDefinition::cessna();
+        heavy.mass.mass *= 2.0;
+        let mut light = Aircraft::flying_high();
+        let mut heavy = Aircraft::from_definition(&heavy).airborne(State::flying_high());
+        for _ in 0..100 {
+            light.do_step(0.01);
+            heavy.do_step(0.01);
+        }
+        assert!(heavy.state.velocity.z < light.state.velocity.z - 1.0);
+    }
+
+    #[test]
+    fn left_turning_tendencies() {
+        // each one on its own, full power and slow with the nose up
+        let turning = |effects: propeller::Effects, pitch_rate: f64| {
+            let mut plane = Aircraft::flying_high();
+            plane.state.velocity = Vec3::new(30.0, 0.0, 0.0);
+            plane.state.attitude = Quaternion::from_angles(&Angles::new(0.0, 10.0, 0.0));
+            plane.state.angular_rate.y = pitch_rate;
+            plane.engine.rpm = 2500.0;
+            plane.propeller_effects = effects;
+            plane.derivative(&plane.state).angular_acceleration
+        };
+        let only = |effects: propeller::Effects| turning(effects, 0.0) - &turning(propeller::Effects::none(), 0.0);
+        // torque rolls it left, p factor and the slipstream swing the nose left
+        let torque = only(propeller::Effects { torque: true, ..propeller::Effects::none() });
+        assert!(torque.x < -0.1);
+        let p_factor = only(propeller::Effects { p_factor: true, ..propeller::Effects::none() });
+        assert!(p_factor.z > 0.01);
+        let slipstream = only(propeller::Effects { slipstream: true, ..propeller::Effects::none() });
+        assert!(slipstream.z > 0.01);
+        // pulling the nose up the prop precesses it to the right
+        let gyroscopic = turning(propeller::Effects { gyroscopic: true, ..propeller::Effects::none() }, -0.5) - &turning(propeller::Effects::none(), -0.5);
+        assert!(gyroscopic.z < -0.01);
+        assert_eq!(only(propeller::Effects::none()).magnitude(), 0.0);
+    }
+
+    #[test]
+    fn spins_and_recovers() {
+        // power off, ease the stick back until it breaks, then full back stick and full left rudder
+        // on the wing and fin, the derivatives dont stall so they dont spin
+        let mut plane = Aircraft::flying_high();
+        plane.aero_model = AircraftDefinition::cessna().aero_model(false);
+        plane.throttle_percent = 0.0;
+        while !plane.wing.stalled {
+            plane.controls.elevator_by(0.015);
+            plane.do_step(0.005);
+        }
+        plane.controls.elevator_by(30.0);
+        plane.controls.rudder_by(20.0);
+        let height = plane.state.position.z;
+        let mut fastest: f64 = 0.0;
+        for step in 0..800 {
+            plane.do_step(0.005);
+            if step >= 200 {
+                // once its going it keeps going round to the left with the wing stalled, slow and coming down
+                assert!(plane.wing.stalled);
+                assert!(plane.state.angular_rate.z > 0.4);
+                assert!(plane.state.airspeed() < 40.0);
+            }
+            fastest = fastest.max(plane.state.angular_rate.z);
+        }
+        assert!(fastest > 1.0);
+        assert!(plane.state.position.z < height - 20.0);
+
+        // PARE, power idle, ailerons neutral, rudder against it and the stick forward
+        plane.controls.aileron = 0.0;
+        plane.controls.rudder_by(-40.0);
+        plane.controls.elevator = 0.0;
+        plane.controls.elevator_by(-5.0);
+        for _ in 0..600 {
+            plane.do_step(0.005);
+            // and the rudder back to the middle once the rotation stops
+            if plane.state.angular_rate.z < 0.1 {
+                plane.controls.rudder = 0.0;
+            }
+        }
+        assert!(!plane.wing.stalled);
+        assert!(plane.state.angular_rate.z.abs() < 0.5);
+        assert!(plane.get_alpha().abs() < 0.2);
+    }
+
+    #[test]
+    fn loading_moves_the_cg() {
+        // bags in the back move the CG behind the wing, so the same lift pitches the nose up harder
+        let mut plane = Aircraft::flying_high();
+        let before = plane.free_body_diagram(&plane.state).1.y;
+        let arm = plane.cg_arm();
+        plane.balance.stations.iter_mut().filter(|station| station.name == "baggage A").for_each(|station| station.mass = 50.0);
+        plane.update_mass();
+        assert!(plane.cg_arm() > arm);
+        assert!(plane.free_body_diagram(&plane.state).1.y < before);
+
+        // and the fuel goes as it flies
+        let (mass, fuel) = (plane.mass(), plane.balance.fuel());
+        for _ in 0..1000 {
+            plane.do_step(0.01);
+        }
+        assert!(plane.balance.fuel() < fuel - 0.05);
+        assert!((mass - plane.mass() - (fuel - plane.balance.fuel())).abs() < 1e-9);
+    }
+
+    #[test]
+    fn runs_out_of_fuel() {
+        // the left tank is nearly empty, once its gone the engine stops until you switch tanks
+        let mut plane = Aircraft::flying_high();
+        plane.balance.tanks[0].fuel = 0.05;
+        plane.fuel.selector = fuel::Selector::Left;
+        for _ in 0..3000 {
+            plane.do_step(0.01);
+        }
+        assert!(plane.engine.starved);
+        assert!(plane.engine.power < 0.0);
+        assert_eq!(plane.balance.tanks[0].fuel, 0.0);
+        assert_eq!(plane.balance.tanks[1].fuel, plane.balance.tanks[1].capacity);
+        assert!(plane.log().contains("\"engine_starved\": true"));
+
+        plane.fuel.selector = fuel::Selector::Right;
+        for _ in 0..300 {
+            plane.do_step(0.01);
+        }
+        assert!(!plane.engine.starved);
+        assert!(plane.engine.power > 0.0);
+    }
+
+    #[test]
+    fn flies_a_jsbsim_plane() {
+        // the c172x tables should sit still on the runway and fly about like our own 172 does
+        let definition = AircraftDefinition::load("aircraft/synthetic_jsbsim/aircraft/c172x/c172x.xml").unwrap();
+        let mut parked = Aircraft::from_definition(&definition);
+        parked.gear.left_brake = 1.0;
+        parked.gear.right_brake = 1.0;
+        for _ in 0..200 {
+            assert!(parked.do_step(0.005).is_none());
+        }
+        assert!(parked.state.velocity.magnitude() < 0.05);
+        assert!(parked.gear.on_ground(&parked.state));
+
+        let mut plane = Aircraft::from_definition(&definition).airborne(State::flying_high());
+        // the tables dont know about the left turning tendencies and nobodys on the rudder, so leave them out
+        plane.propeller_effects = propeller::Effects::none();
+        for _ in 0..500 {
+            plane.do_step(0.01);
+        }
+        assert!(plane.state.position.z.is_finite());
+        assert!(plane.state.velocity.magnitude() > 30.0 && plane.state.velocity.magnitude() < 70.0);
+        assert!(plane.state.pointing_global().roll.abs() < 5.0);
+    }
+
+    #[test]
+    fn flies_on_the_wing_and_fin() {
+        // swapping the stability derivatives for the wing and fin still sits still and flies level
+        let definition = AircraftDefinition::cessna();
+        let mut parked = Aircraft::from_definition(&definition);
+        parked.aero_model = definition.aero_model(false);
+        parked.gear.left_brake = 1.0;
+        parked.gear.right_brake = 1.0;
+        for _ in 0..200 {
+            assert!(parked.do_step(0.005).is_none());
+        }
+        assert!(parked.state.velocity.magnitude() < 0.05);
+
+        let mut plane = Aircraft::flying_high();
+        plane.aero_model = definition.aero_model(false);
+        plane.propeller_effects = propeller::Effects::none();
+        for _ in 0..500 {
+            plane.do_step(0.01);
+        }
+        assert!(plane.state.velocity.magnitude() > 30.0 && plane.state.velocity.magnitude() < 70.0);
+        assert!(plane.state.pointing_global().roll.abs() < 5.0);
+    }
+
+    #[test]
+    fn flaps_lift_and_drag() {
+        let forces = |notches: usize| {
+            let mut plane = Aircraft::flying_high();
+            for _ in 0..notches {
+                plane.flaps.lever_down();
+            }
+            for _ in 0..100 {
+                plane.flaps.step(0.1, 30.0);
+            }
+            plane.free_body_diagram(&plane.state).0
+        };
+        let clean = forces(0);
+        let landing = forces(3);
+        assert!(landing.z > clean.z);
+        assert!(landing.x < clean.x);
+    }
+
+    #[test]
+    fn flap_limits_go_on_indicated() {
+        // 75 m/s true up at 4km is only about 61 indicated, fast for the first notch but not enough to bend them
+        let mut plane = Aircraft::flying_high();
+        plane.flaps.lever_down();
+        plane.flaps.position = 10.0;
+        plane.state.position.z = 4000.0;
+        plane.state.velocity = Vec3::new(75.0, 0.0, 0.0);
+        assert!((plane.equivalent_airspeed() - 61.3).abs() < 0.5);
+        plane.do_step(0.01);
+        assert!(!plane.flaps.damaged);
+        assert!(plane.flaps.overspeed(plane.equivalent_airspeed()));
+
+        // the same down low breaks them
+        plane.state.position.z = 100.0;
+        plane.state.velocity = Vec3::new(75.0, 0.0, 0.0);
+        plane.do_step(0.01);
+        assert!(plane.flaps.damaged);
+    }
+
+    #[test]
+    fn stalls_when_pulled_too_hard() {
+        // full up elevator with the power off and it should end up stalled, with the horn going first
+        let mut plane = Aircraft::flying_high();
+        plane.throttle_percent = 0.0;
+        plane.controls.elevator_by(30.0);
+        let mut horn_before_stall = false;
+        for _ in 0..1000 {
+            plane.do_step(0.01);
+            if plane.wing.stalled {
+                break;
+            }
+            horn_before_stall |= plane.wing.is_stalling(plane.get_alpha());
+        }
+        assert!(plane.wing.stalled);
+        assert!(horn_before_stall);
+        assert!(plane.log().contains("\"stall_warning\": true"));
+    }
+
+    #[test]
+    fn integrators_converge() {
+        // fly the same 5 seconds with smaller and smaller steps, the answers should close up
+        let final_position = |integrator: Integrator, dt: f64| {
+            let mut plane = Aircraft { integrator, ..Aircraft::flying_high() };
+            plane.controls.elevator_by(-3.0);
+            for _ in 0..(5.0 / dt).round() as usize {
+                plane.do_step(dt);
+            }
+            plane.state.position
+        };
+        for integrator in [Integrator::SemiImplicitEuler, Integrator::RungeKutta4] {
+            let coarse = final_position(integrator, 0.04);
+            let medium = final_position(integrator, 0.02);
+            let fine = final_position(integrator, 0.01);
+            assert!((fine - &medium).magnitude() < (medium - &coarse).magnitude());
+        }
+        let adaptive = final_position(Integrator::DormandPrince { tolerance: 1e-6 }, 0.01);
+        assert!((adaptive - &final_position(Integrator::RungeKutta4, 0.01)).magnitude() < 1.0);
+    }
+
+    #[test]
+    fn test_alpha() {
+        let state = State {
+            attitude: Quaternion::from_angles(&Angles::new(0.0, 0.0, 0.0)),
+            position: Vec3::new(0.0, 0.0, 0.0),
+            angular_rate: Vec3::new(0.0, 0.0, 0.0),
+            velocity: Vec3::new(10.0, 0.0, 1.0),
+            acceleration: Vec3::new(0.0, 0.0, 0.0),
+            wind: Vec3::new(0.0, 0.0, 0.0),
+            gust_rate: Vec3::new(0.0, 0.0, 0.0),
+        };
+        let mut plane = Aircraft {
+            state,
+            throttle_percent: 0.0,
+            mass: 10.0,
+            ..Aircraft::new()
+        };
+        let target = -0.1_f64.atan();
+        let answer = plane.get_alpha();
+        assert!((target - answer).abs() < 1e-6);
+
+        // pointing the way its going, alpha doesnt care about heading
+        plane.state.velocity = Vec3::new(0.0, 10.0, 1.0);
+        plane.state.attitude = Quaternion::from_angles(&Angles::new(90.0, 0.0, 0.0));
+        let target = -0.1_f64.atan();
+        let answer = plane.get_alpha();
+        assert!((target - answer).abs() < 1e-6);
+        assert!(plane.get_sideslip().abs() < 1e-6);
+
+        // but going sideways to where its pointing is all sideslip, from the right here
+        plane.state.velocity = Vec3::new(0.0, -10.0, 0.0);
+        plane.state.attitude = Quaternion::from_angles(&Angles::new(0.0, 0.0, 0.0));
+        assert!(plane.get_alpha().abs() < 1e-6);
+        assert!((plane.get_sideslip() - std::f64::consts::PI / 2.0).abs() < 1e-6);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,104 @@
+// the international standard atmosphere, good up to 20km which is way more than a cessna needs
+const SEA_LEVEL_TEMPERATURE: f64 = 288.15; // K
+const SEA_LEVEL_PRESSURE: f64 = 101325.0; // Pa
+const LAPSE_RATE: f64 = 0.0065; // K/m
+const TROPOPAUSE: f64 = 11000.0; // m
+const GAS_CONSTANT: f64 = 287.053; // J/kg/K for dry air
+const GAMMA: f64 = 1.4;
+const GRAVITY: f64 = 9.80665;
+
+#[derive(Clone, Copy, Debug)]
+#[allow(dead_code)]
+pub struct AirProperties {
+    pub pressure: f64, // Pa
+    pub temperature: f64, // K
+    pub density: f64, // kg/m^3
+    pub speed_of_sound: f64, // m/s
+}
+
+pub struct Atmosphere {
+    pub temperature_offset: f64, // K, so ISA+15 on a hot day
+    pub sea_level_pressure: f64, // Pa, the QNH
+}
+
+impl Atmosphere {
+    pub fn new() -> Atmosphere {
+        Atmosphere {
+            temperature_offset: 0.0,
+            sea_level_pressure: SEA_LEVEL_PRESSURE,
+        }
+    }
+
+    pub fn with_offsets(temperature_offset: f64, sea_level_pressure: f64) -> Atmosphere {
+        Atmosphere {
+            temperature_offset,
+            sea_level_pressure,
+        }
+    }
+
+    pub fn at(&self, altitude: f64) -> AirProperties {
+        /*
+        pressure follows the standard lapse rate from whatever the sea level pressure is,
+        the temperature offset only changes the temperature and so the density
+         */
+        let altitude = altitude.clamp(-1000.0, 20000.0);
+        let exponent = GRAVITY / (GAS_CONSTANT * LAPSE_RATE);
+        let (standard_temperature, pressure) = if altitude <= TROPOPAUSE {
+            let temperature = SEA_LEVEL_TEMPERATURE - LAPSE_RATE * altitude;
+            (temperature, self.sea_level_pressure * (temperature / SEA_LEVEL_TEMPERATURE).powf(exponent))
+        } else {
+            let temperature = SEA_LEVEL_TEMPERATURE - LAPSE_RATE * TROPOPAUSE;
+            let tropopause_pressure = self.sea_level_pressure * (temperature / SEA_LEVEL_TEMPERATURE).powf(exponent);
+            (temperature, tropopause_pressure * (-GRAVITY * (altitude - TROPOPAUSE) / (GAS_CONSTANT * temperature)).exp())
+        };
+        let temperature = standard_temperature + self.temperature_offset;
+        AirProperties {
+            pressure,
+            temperature,
+            density: pressure / (GAS_CONSTANT * temperature),
+            speed_of_sound: (GAMMA * GAS_CONSTANT * temperature).sqrt(),
+        }
+    }
+
+    pub fn density_altitude(&self, altitude: f64) -> f64 {
+        // the altitude in the standard atmosphere that has the same density, only right in the troposphere
+        let ratio = self.at(altitude).density / sea_level_density();
+        SEA_LEVEL_TEMPERATURE / LAPSE_RATE * (1.0 - ratio.powf(1.0 / (GRAVITY / (GAS_CONSTANT * LAPSE_RATE) - 1.0)))
+    }
+}
+
+pub fn sea_level_density() -> f64 {
+    SEA_LEVEL_PRESSURE / (GAS_CONSTANT * SEA_LEVEL_TEMPERATURE)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn matches_isa_tables() {
+        let atmosphere = Atmosphere::new();
+        let sea_level = atmosphere.at(0.0);
+        assert!((sea_level.density - 1.225).abs() < 1e-3);
+        assert!((sea_level.speed_of_sound - 340.3).abs() < 0.1);
+
+        let one_km = atmosphere.at(1000.0);
+        assert!((one_km.temperature - 281.65).abs() < 1e-6);
+        assert!((one_km.pressure - 89876.0).abs() < 10.0);
+        assert!((one_km.density - 1.1117).abs() < 1e-3);
+
+        let stratosphere = atmosphere.at(15000.0);
+        assert!((stratosphere.temperature - 216.65).abs() < 1e-6);
+        assert!((stratosphere.pressure - 12045.0).abs() < 5.0);
+    }
+
+    #[test]
+    fn hot_day_is_thin() {
+        let hot = Atmosphere::with_offsets(20.0, SEA_LEVEL_PRESSURE);
+        let standard = Atmosphere::new();
+        assert!(hot.at(500.0).density < standard.at(500.0).density);
+        assert!((hot.at(500.0).pressure - standard.at(500.0).pressure).abs() < 1e-6);
+        assert!((hot.density_altitude(500.0) - 1193.0).abs() < 1.0);
+        assert!((standard.density_altitude(500.0) - 500.0).abs() < 1.0);
+    }
+}
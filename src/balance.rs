@@ -0,0 +1,174 @@
+use serde::Deserialize;
+
+use crate::common_math::Vec3;
+use crate::definition::MassProperties;
+
+// somewhere to put people or bags, in body coords from the reference point like the rest of the plane
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Station {
+    pub name: String,
+    pub position: Vec3,
+    pub mass: f64, // kg
+    pub max: f64, // kg, the placard limit
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Tank {
+    pub name: String,
+    pub position: Vec3,
+    pub fuel: f64, // kg
+    pub capacity: f64, // kg of usable fuel
+}
+
+/*
+weight and balance, the way the POH does it. the loading moves the CG around the reference point
+that the aero, gear and thrust are all measured from, and the fuel gets burnt out of the tanks as it goes
+ */
+#[derive(Deserialize, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Balance {
+    #[serde(default)]
+    pub datum: Vec3, // where the arms are measured back from, in body coords
+    #[serde(default)]
+    pub stations: Vec<Station>,
+    #[serde(default)]
+    pub tanks: Vec<Tank>,
+    #[serde(default)]
+    pub envelope: Vec<(f64, f64)>, // arm in m aft of the datum against mass in kg, all the way round the edge
+}
+
+// what the plane weighs as loaded right now
+#[derive(Clone, Copy, Debug)]
+pub struct Loading {
+    pub mass: f64, // kg
+    pub cg: Vec3, // body coords from the reference point
+    pub inertia: [f64; 4], // ixx, iyy, izz, ixz about the CG
+}
+
+impl Balance {
+    pub fn fuel(&self) -> f64 {
+        self.tanks.iter().map(|tank| tank.fuel).sum()
+    }
+
+    pub fn draw(&mut self, amount: f64, feeds: impl Fn(&Tank) -> bool) -> f64 {
+        // evenly out of whichever of the feeding tanks have anything left, hands back how much it actually got
+        let mut left = amount;
+        while left > 1e-12 {
+            let feeding = self.tanks.iter().filter(|tank| tank.fuel > 0.0 && feeds(tank)).count();
+            if feeding == 0 {
+                break;
+            }
+            let share = left / feeding as f64;
+            for tank in self.tanks.iter_mut().filter(|tank| tank.fuel > 0.0 && feeds(tank)) {
+                let taken = share.min(tank.fuel);
+                tank.fuel -= taken;
+                left -= taken;
+            }
+        }
+        amount - left
+    }
+
+    pub fn loaded(&self, empty: &MassProperties) -> Loading {
+        // everything is a point mass apart from the empty plane, which brings its own inertia about its own CG
+        let masses: Vec<(f64, Vec3)> = std::iter::once((empty.mass, empty.cg))
+            .chain(self.stations.iter().map(|station| (station.mass, station.position)))
+            .chain(self.tanks.iter().map(|tank| (tank.fuel, tank.position)))
+            .collect();
+        let mass: f64 = masses.iter().map(|(part, _)| part).sum();
+        let cg = masses.iter().fold(Vec3::new(0.0, 0.0, 0.0), |sum, (part, position)| sum + &(*position * *part)) * (1.0 / mass);
+
+        let mut inertia = empty.inertia;
+        for (part, position) in &masses {
+            let offset = *position - &cg;
+            inertia[0] += part * (offset.y.powf(2.0) + offset.z.powf(2.0));
+            inertia[1] += part * (offset.x.powf(2.0) + offset.z.powf(2.0));
+            inertia[2] += part * (offset.x.powf(2.0) + offset.y.powf(2.0));
+            inertia[3] += part * offset.x * offset.z;
+        }
+        Loading { mass, cg, inertia }
+    }
+
+    pub fn arm(&self, cg: &Vec3) -> f64 {
+        // how far back from the datum, which is how the POH measures it
+        self.datum.x - cg.x
+    }
+
+    pub fn problems(&self, loading: &Loading) -> Vec<String> {
+        // anything that should keep you on the ground, nothing means its good to go
+        let mut problems = Vec::new();
+        for station in &self.stations {
+            if station.mass > station.max {
+                problems.push(format!("{} is {:.0}kg over", station.name, station.mass - station.max));
+            }
+        }
+        if !self.envelope.is_empty() && !inside(&self.envelope, self.arm(&loading.cg), loading.mass) {
+            let heaviest = self.envelope.iter().map(|(_, mass)| *mass).fold(0.0, f64::max);
+            if loading.mass > heaviest {
+                problems.push(format!("{:.0}kg over max weight", loading.mass - heaviest));
+            } else {
+                problems.push(format!("CG at {:.3}m is outside the envelope", self.arm(&loading.cg)));
+            }
+        }
+        problems
+    }
+}
+
+fn inside(polygon: &[(f64, f64)], x: f64, y: f64) -> bool {
+    // count how many edges a line off to the right crosses, odd means its inside
+    let mut crossings = 0;
+    for i in 0..polygon.len() {
+        let (x0, y0) = polygon[i];
+        let (x1, y1) = polygon[(i + 1) % polygon.len()];
+        if (y0 > y) != (y1 > y) && x < x0 + (y - y0) * (x1 - x0) / (y1 - y0) {
+            crossings += 1;
+        }
+    }
+    crossings % 2 == 1
+}
+
+#[cfg(test)]
+mod test {
+    use crate::definition::AircraftDefinition;
+
+    #[test]
+    fn loads_like_the_poh() {
+        let definition = AircraftDefinition::cessna();
+        let balance = definition.balance.clone();
+        let loading = balance.loaded(&definition.mass);
+        // the usual load comes out at max weight with the CG on the reference point
+        assert!((loading.mass - 1156.0).abs() < 1.0);
+        assert!(loading.cg.magnitude() < 0.01);
+        assert!((loading.inertia[1] - 1825.0).abs() < 10.0);
+        assert!(balance.problems(&loading).is_empty());
+
+        // bags right at the back move it aft and make it too heavy
+        let mut loaded_up = balance.clone();
+        loaded_up.stations.iter_mut().filter(|station| station.name.starts_with("baggage")).for_each(|station| station.mass = station.max);
+        let heavy = loaded_up.loaded(&definition.mass);
+        assert!(heavy.cg.x < loading.cg.x);
+        assert!(!loaded_up.problems(&heavy).is_empty());
+
+        // two big people up front, nobody in the back and hardly any fuel puts the CG too far forward
+        let mut nose_heavy = balance.clone();
+        for station in nose_heavy.stations.iter_mut() {
+            station.mass = if station.position.x > 0.0 { station.max } else { 0.0 };
+        }
+        nose_heavy.tanks.iter_mut().for_each(|tank| tank.fuel = 5.0);
+        let forward = nose_heavy.loaded(&definition.mass);
+        assert!(nose_heavy.problems(&forward)[0].contains("outside the envelope"));
+    }
+
+    #[test]
+    fn burns_evenly_and_runs_dry() {
+        let mut balance = AircraftDefinition::cessna().balance;
+        balance.tanks[0].fuel = 1.0;
+        // half each until the left one runs out, then the rest from the right
+        assert_eq!(balance.draw(4.0, |_| true), 4.0);
+        assert_eq!(balance.tanks[0].fuel, 0.0);
+        assert!((balance.tanks[1].fuel - (balance.tanks[1].capacity - 3.0)).abs() < 1e-9);
+        assert!(balance.draw(1000.0, |_| true) < 1000.0);
+        assert_eq!(balance.fuel(), 0.0);
+    }
+}
@@ -0,0 +1,71 @@
+use std::time::Instant;
+
+// physics runs at a fixed rate however long the frames take, and the renderer gets told how far between steps it is
+pub const PHYSICS_RATE: f64 = 200.0; // Hz
+const MAX_FRAME_TIME: f64 = 0.25; // s, if a frame takes longer than this we just let the sim fall behind
+
+pub struct Clock {
+    pub step: f64, // s of sim time per physics step
+    accumulator: f64, // real time thats passed but not been simulated yet
+    last_tick: Option<Instant>,
+}
+
+impl Clock {
+    pub fn new() -> Clock {
+        Clock {
+            step: 1.0 / PHYSICS_RATE,
+            accumulator: 0.0,
+            last_tick: None,
+        }
+    }
+
+    pub fn tick(&mut self) -> (usize, f64) {
+        // call once a frame, gives back how many physics steps to run and how long the frame was
+        let now = Instant::now();
+        let frame_time = match self.last_tick {
+            Some(last) => (now - last).as_secs_f64(),
+            None => 0.0,
+        };
+        self.last_tick = Some(now);
+        (self.advance_by(frame_time), frame_time)
+    }
+
+    pub fn advance_by(&mut self, frame_time: f64) -> usize {
+        /*
+        a really slow frame (dragging the window, a breakpoint) would otherwise make us run
+        thousands of steps to catch up, which makes the next frame slow too, and so on
+         */
+        self.accumulator += frame_time.clamp(0.0, MAX_FRAME_TIME);
+        let steps = (self.accumulator / self.step).floor();
+        self.accumulator -= steps * self.step;
+        steps as usize
+    }
+
+    pub fn blend(&self) -> f64 {
+        // how far we are from the last physics step to the next one, 0 to 1
+        (self.accumulator / self.step).clamp(0.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn keeps_real_time() {
+        // uneven frames should still add up to the same number of steps
+        let mut clock = Clock::new();
+        let mut steps = 0;
+        for frame_time in [0.003, 0.021, 0.0165, 0.05, 0.0095, 0.0025] {
+            steps += clock.advance_by(frame_time);
+        }
+        assert_eq!(steps, 20);
+        assert!(clock.blend() >= 0.0 && clock.blend() < 1.0);
+    }
+
+    #[test]
+    fn doesnt_spiral_after_a_stall() {
+        let mut clock = Clock::new();
+        assert_eq!(clock.advance_by(10.0), (MAX_FRAME_TIME * PHYSICS_RATE) as usize);
+    }
+}
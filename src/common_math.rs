@@ -1,307 +1,624 @@
-use core::f64;
-
-#[derive(Clone, Copy, Debug)]
-pub struct Vec3 {
-    pub x: f64,
-    pub y: f64,
-    pub z: f64,
-}
-
-#[derive(Clone, Copy, Debug)]
-pub struct Angles {
-    pub azimouth: f64,
-    pub altitude: f64,
-    pub roll: f64,
-}
-
-impl Vec3 {
-    pub fn new(x: f64, y: f64, z: f64) -> Vec3 {
-        Vec3{
-            x,
-            y,
-            z
-        }
-    }
-
-    #[allow(dead_code)]
-    pub fn magnitude(&self) -> f64 {
-        (self.x.powf(2.0) + self.y.powf(2.0) + self.z.powf(2.0)).powf(0.5)
-    }
-
-    #[allow(dead_code)]
-    pub fn unit_vector(&self) -> Option<Vec3> {
-        let mag = self.magnitude();
-        if mag == 0_f64{
-            return None;
-        }
-        Some(Vec3 {
-            x: self.x / mag,
-            y: self.y / mag,
-            z: self.z / mag,
-        })
-    }
-
-    pub fn transform_coordinates(&self, pointing: &Angles) ->  Vec3 {
-        /*
-        takes in a azimouth altitude roll of a plane and returns the relevant scaling factors
-        for thrust, this would take in the thrust in body coords and convert it into thrust in world coords
-         */
-        Vec3{
-            x:  self.x * deg_to_rad(pointing.altitude).cos() * deg_to_rad(pointing.azimouth).cos() +
-                self.z * deg_to_rad(pointing.roll).sin() * deg_to_rad(pointing.azimouth).sin() - 
-                self.y * deg_to_rad(pointing.azimouth).sin() * deg_to_rad(pointing.roll).cos(),
-
-            y:  self.x * deg_to_rad(pointing.altitude).cos() * deg_to_rad(pointing.azimouth).sin() + 
-                self.z * deg_to_rad(pointing.roll).sin() + 
-                self.y * deg_to_rad(pointing.azimouth).cos() * deg_to_rad(pointing.roll).cos(),
-                
-            z:  self.x * deg_to_rad(pointing.altitude).sin() +
-                self.y * deg_to_rad(pointing.roll).sin() + 
-                self.z * deg_to_rad(pointing.altitude).cos() * deg_to_rad(pointing.roll).cos()
-        }
-    }
-
-    pub fn dot_product(&self, other: &Vec3) -> f64 {
-        return (self.x * other.x) + (self.y * other.y) + (self.z * other.z);
-    }
-
-    pub fn angle_with(&self, other: &Vec3) -> f64 {
-        let fraction = self.dot_product(other) / (self.magnitude() * other.magnitude());
-        if fraction.is_nan(){
-            return f64::consts::PI/2.0;
-        }
-        return fraction.acos();
-    }
-
-    pub fn jsonify(&self) -> String {
-        format!("{{\"x\": {}, \"y\": {}, \"z\": {}}}", self.x, self.y, self.z)
-    }
-
-    pub fn angle_with_horizon(&self) -> f64 {
-        let horizontal = (self.x.powf(2.0) + self.y.powf(2.0)).powf(0.5);
-        self.z.atan2(horizontal)
-    }
-}
-
-impl Angles {
-    pub fn new(azimouth: f64, altitude: f64, roll: f64) -> Angles {
-        Angles {
-            azimouth: azimouth,
-            altitude: altitude, 
-            roll,
-        }
-    }
-    
-    #[allow(dead_code)]
-    pub fn as_vec3(&self) -> Vec3 {
-        let x_azimouth = deg_to_rad(self.azimouth).cos();
-        let x_altitude = x_azimouth * deg_to_rad(self.altitude).cos();
-
-        let y_azimouth = deg_to_rad(self.azimouth).sin();
-        let y_altitude = y_azimouth * deg_to_rad(self.altitude).cos();
-        
-        let z_altitude = deg_to_rad(self.altitude).sin();
-
-        let x = x_altitude; 
-        let y = y_altitude;
-        let z = z_altitude;
-        Vec3 {x, y, z}
-    }
-    
-    pub fn jsonify(&self) -> String {
-        format!("{{\"alt\": {}, \"az\": {}, \"roll\": {}}}", self.altitude, self.azimouth, self.roll)
-    }
-}
-
-impl std::ops::Add<&Vec3> for Vec3 {
-    type Output = Vec3;
-    fn add(self, other: &Vec3) -> Vec3 {
-        Vec3 {
-            x: self.x + other.x,
-            y: self.y + other.y,
-            z: self.z + other.z,
-        }
-    }
-}
-
-impl std::ops::Mul<f64> for Vec3 {
-    type Output = Vec3;
-    fn mul(self, other: f64) -> Vec3 {
-        Vec3{
-            x: self.x * other,
-            y: self.y * other,
-            z: self.z * other,
-        }
-    }
-}
-
-impl std::ops::Add<&Angles> for Angles {
-    type Output = Angles;
-    fn add(self, other: &Angles) -> Angles {
-        Angles {
-            azimouth: self.azimouth + other.azimouth,
-            altitude: self.altitude + other.altitude,
-            roll: self.roll + other.roll,
-        }
-    }
-}
-
-impl std::ops::Mul<f64> for Angles {
-    type Output = Angles;
-    fn mul(self, other: f64) -> Angles {
-        Angles {
-            altitude: self.altitude * other,
-            azimouth: self.azimouth * other,
-            roll: self.roll * other,
-        }
-    }
-}
-
-impl PartialEq for Vec3 {
-    fn eq(&self, other: &Self) -> bool {
-        (self.x - other.x).abs() < 1e-10 && 
-        (self.y - other.y).abs() < 1e-10 && 
-        (self.z - other.z).abs() < 1e-10 
-    }
-}
-
-#[allow(dead_code)]
-pub fn rad_to_deg(radians: f64) -> f64 {
-    radians * 180.0 / std::f64::consts::PI
-}
-
-pub fn deg_to_rad(degrees: f64) -> f64 {
-    degrees * std::f64::consts::PI / 180.0  
-}
-
-#[cfg(test)]
-mod test{
-    use core::f64;
-
-    use super::*;
-    #[test]
-    fn deg_to_rad_correct() {
-        assert_eq!(deg_to_rad(180.0), std::f64::consts::PI);
-    }
-
-    #[test]
-    fn magnitude_correct() {
-        let thing = Vec3::new(2.0, 3.0, 4.0);
-        assert_eq!(thing.magnitude(), 29_f64.sqrt())
-    }
-
-    #[test]
-    fn unit_vector_correct() {
-        //TODO regression
-    }
-
-    #[test]
-    fn coordinate_transform_correct() {
-        // case 1: azimouthed 90 degrees to right, thrust pointing forwards
-        let test_transform_1 = Vec3::new(1.0, 0.0, 0.0);
-        let pointing_1 = Angles::new(90.0, 0.0, 0.0);
-        let answer_1 = Vec3::new(0.0, 1.0, 0.0); 
-        let check_1 = test_transform_1.transform_coordinates(&pointing_1);
-        assert!((answer_1.x - check_1.x).abs() < 1e-10);
-        assert!((answer_1.y - check_1.y).abs() < 1e-10);
-        assert!((answer_1.z - check_1.z).abs() < 1e-10);
-        assert!((answer_1.magnitude() - 1.0).abs() < 1e-10);
-
-        // case 2: azimouthed 90 degrees to left, thrust pointing forwards
-        let test_transform_2 = Vec3::new(1.0, 0.0, 0.0);
-        let pointing_2 = Angles::new(-90.0, 0.0, 0.0);
-        let answer_2 = Vec3::new(0.0, -1.0, 0.0); 
-        let check_2 = test_transform_2.transform_coordinates(&pointing_2);
-        assert!((answer_2.x - check_2.x).abs() < 1e-10);
-        assert!((answer_2.y - check_2.y).abs() < 1e-10);
-        assert!((answer_2.z - check_2.z).abs() < 1e-10);
-        assert!((answer_2.magnitude() - 1.0).abs() < 1e-10);
-        
-        // case 3: azimouthed 90 degrees to right, thrust pointing right
-        let test_transform_3 = Vec3::new(0.0, 1.0, 0.0);
-        let pointing_3 = Angles::new(90.0, 0.0, 0.0);
-        let answer_3 = Vec3::new(-1.0, 0.0, 0.0); 
-        let check_3 = test_transform_3.transform_coordinates(&pointing_3);
-        assert!((answer_3.x - check_3.x).abs() < 1e-10);
-        assert!((answer_3.y - check_3.y).abs() < 1e-10);
-        assert!((answer_3.z - check_3.z).abs() < 1e-10);
-        assert!((answer_3.magnitude() - 1.0).abs() < 1e-10);
-
-        // case 4: azimouthed 90 degrees to the left, thrust pointing right
-        let test_transform_4 = Vec3::new(0.0, 1.0, 0.0);
-        let pointing_4 = Angles::new(-90.0, 0.0, 0.0);
-        let answer_4 = Vec3::new(1.0, 0.0, 0.0); 
-        let check_4 = test_transform_4.transform_coordinates(&pointing_4);
-        assert!((answer_4.x - check_4.x).abs() < 1e-10);
-        assert!((answer_4.y - check_4.y).abs() < 1e-10);
-        assert!((answer_4.z - check_4.z).abs() < 1e-10);
-        assert!((answer_4.magnitude() - 1.0).abs() < 1e-10);
-
-        // case 5: altitude 45 degrees up and azimouth 45 degrees to the right, thrust pointing forwards
-        let test_transform_5 = Vec3::new(1.0, 0.0, 0.0);
-        let pointing_5 = Angles::new(45.0, 45.0, 0.0);
-        let answer_5 = Vec3::new(1.0 / 2.0_f64.sqrt() * 1.0 / 2.0_f64.sqrt(), 1.0 / 2.0_f64.sqrt() * 1.0 / 2.0_f64.sqrt(), 1.0 / 2.0_f64.sqrt()); 
-        let check_5 = test_transform_5.transform_coordinates(&pointing_5);
-        assert!((answer_5.x - check_5.x).abs() < 1e-10);
-        assert!((answer_5.y - check_5.y).abs() < 1e-10);
-        assert!((answer_5.z - check_5.z).abs() < 1e-10);
-        assert!((answer_5.magnitude() - 1.0).abs() < 1e-10);
-        
-        // case 6: altitude 10 degrees up and roll 10 degrees left
-        let test_transform_6 = Vec3::new(1.0, 0.0, 0.0);
-        let pointing_6 = Angles::new(0.0, 10.0, 10.0);
-        let answer_6 = Vec3::new(deg_to_rad(10.0).cos(), 0.0, deg_to_rad(10.0).sin()); 
-        let check_6 = test_transform_6.transform_coordinates(&pointing_6);
-        assert!((answer_6.x - check_6.x).abs() < 1e-10);
-        assert!((answer_6.y - check_6.y).abs() < 1e-10);
-        assert!((answer_6.z - check_6.z).abs() < 1e-10);
-        assert!((answer_6.magnitude() - 1.0).abs() < 1e-10);
-
-        // case 7
-        let test_transform_7 = Vec3::new(1.0, 0.0, 0.0);
-        let pointing_7 = Angles::new(0.0, -10.0, 0.0);
-        let answer_7 = Vec3::new(deg_to_rad(-10.0).cos(), 0.0, deg_to_rad(-10.0).sin()); 
-        let check_7 = test_transform_7.transform_coordinates(&pointing_7);
-        assert!((answer_7.x - check_7.x).abs() < 1e-10);
-        assert!((answer_7.y - check_7.y).abs() < 1e-10);
-        assert!((answer_7.z - check_7.z).abs() < 1e-10);
-        assert!((answer_7.magnitude() - 1.0).abs() < 1e-10);
-
-        // case 8
-        let test_transform_8 = Vec3::new(0.0, 0.0, 1.0);
-        let pointing_8 = Angles::new(0.0, 0.0, 90.0);
-        let answer_8 = Vec3::new(0.0, 1.0, 0.0); 
-        let check_8 = test_transform_8.transform_coordinates(&pointing_8);
-        assert!((answer_8.x - check_8.x).abs() < 1e-10);
-        assert!((answer_8.y - check_8.y).abs() < 1e-10);
-        assert!((answer_8.z - check_8.z).abs() < 1e-10);
-        assert!((answer_8.magnitude() - 1.0).abs() < 1e-10);
-        //TODO extend test suite
-    }
-
-    #[test]
-    fn test_angle_with() {
-        let vec1_1 = Vec3::new(0.0, 1.0, 0.0);
-        let vec1_2 = Vec3::new(0.0, -1.0, 0.0);
-        println!("{}", vec1_1.angle_with(&vec1_2)/f64::consts::PI);
-        let vec2_1 = Vec3::new(1.0, 0.0, 0.0);
-        println!("{}", vec1_1.angle_with(&vec2_1)/f64::consts::PI);
-        let vec2_2 = Vec3::new(1.0, 0.0, 1.0).unit_vector().unwrap();
-        println!("{}, {:?}", vec2_1.angle_with(&vec2_2)/f64::consts::PI, vec2_2)
-    }
-
-    #[test]
-    fn test_angle_to_vec3() {
-        assert_eq!(Angles::new(0.0, 0.0, 0.0).as_vec3(), 
-            Vec3::new(1.0, 0.0, 0.0)
-        );
-
-        assert_eq!(Angles::new(0.0, 90.0, 0.0).as_vec3(), 
-            Vec3::new(0.0, 0.0, 1.0)
-        );
-
-        assert_eq!(Angles::new(90.0, 0.0, 0.0).as_vec3(), 
-            Vec3::new(0.0, 1.0, 0.0)
-        );
-    }
+use core::f64;
+
+use serde::Deserialize;
+
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(from = "[f64; 3]")]
+pub struct Vec3 {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Mat3 {
+    pub data: [[f64; 3]; 3],
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Quaternion {
+    pub w: f64,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Angles {
+    pub azimouth: f64,
+    pub altitude: f64,
+    pub roll: f64,
+}
+
+impl Vec3 {
+    pub fn new(x: f64, y: f64, z: f64) -> Vec3 {
+        Vec3{
+            x,
+            y,
+            z
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn magnitude(&self) -> f64 {
+        (self.x.powf(2.0) + self.y.powf(2.0) + self.z.powf(2.0)).powf(0.5)
+    }
+
+    #[allow(dead_code)]
+    pub fn unit_vector(&self) -> Option<Vec3> {
+        let mag = self.magnitude();
+        if mag == 0_f64{
+            return None;
+        }
+        Some(Vec3 {
+            x: self.x / mag,
+            y: self.y / mag,
+            z: self.z / mag,
+        })
+    }
+
+    #[allow(dead_code)]
+    pub fn transform_coordinates(&self, pointing: &Angles) ->  Vec3 {
+        /*
+        takes in a azimouth altitude roll of a plane and returns the relevant scaling factors
+        for thrust, this would take in the thrust in body coords and convert it into thrust in world coords
+         */
+        Vec3{
+            x:  self.x * deg_to_rad(pointing.altitude).cos() * deg_to_rad(pointing.azimouth).cos() +
+                self.z * deg_to_rad(pointing.roll).sin() * deg_to_rad(pointing.azimouth).sin() - 
+                self.y * deg_to_rad(pointing.azimouth).sin() * deg_to_rad(pointing.roll).cos(),
+
+            y:  self.x * deg_to_rad(pointing.altitude).cos() * deg_to_rad(pointing.azimouth).sin() + 
+                self.z * deg_to_rad(pointing.roll).sin() + 
+                self.y * deg_to_rad(pointing.azimouth).cos() * deg_to_rad(pointing.roll).cos(),
+                
+            z:  self.x * deg_to_rad(pointing.altitude).sin() +
+                self.y * deg_to_rad(pointing.roll).sin() + 
+                self.z * deg_to_rad(pointing.altitude).cos() * deg_to_rad(pointing.roll).cos()
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn dot_product(&self, other: &Vec3) -> f64 {
+        (self.x * other.x) + (self.y * other.y) + (self.z * other.z)
+    }
+
+    pub fn cross_product(&self, other: &Vec3) -> Vec3 {
+        Vec3 {
+            x: self.y * other.z - self.z * other.y,
+            y: self.z * other.x - self.x * other.z,
+            z: self.x * other.y - self.y * other.x,
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn angle_with(&self, other: &Vec3) -> f64 {
+        let fraction = self.dot_product(other) / (self.magnitude() * other.magnitude());
+        if fraction.is_nan(){
+            return f64::consts::PI/2.0;
+        }
+        fraction.acos()
+    }
+
+    pub fn jsonify(&self) -> String {
+        format!("{{\"x\": {}, \"y\": {}, \"z\": {}}}", self.x, self.y, self.z)
+    }
+
+    pub fn angle_with_horizon(&self) -> f64 {
+        let horizontal = (self.x.powf(2.0) + self.y.powf(2.0)).powf(0.5);
+        self.z.atan2(horizontal)
+    }
+}
+
+impl Mat3 {
+    pub fn new(data: [[f64; 3]; 3]) -> Mat3 {
+        Mat3 { data }
+    }
+
+    pub fn inertia(ixx: f64, iyy: f64, izz: f64, ixz: f64) -> Mat3 {
+        // the plane is symmetric left to right so ixy and iyz are always 0
+        Mat3::new([
+            [ixx, 0.0, -ixz],
+            [0.0, iyy, 0.0],
+            [-ixz, 0.0, izz],
+        ])
+    }
+
+    pub fn mul_vec(&self, other: &Vec3) -> Vec3 {
+        let d = &self.data;
+        Vec3 {
+            x: d[0][0] * other.x + d[0][1] * other.y + d[0][2] * other.z,
+            y: d[1][0] * other.x + d[1][1] * other.y + d[1][2] * other.z,
+            z: d[2][0] * other.x + d[2][1] * other.y + d[2][2] * other.z,
+        }
+    }
+
+    pub fn determinant(&self) -> f64 {
+        let d = &self.data;
+        d[0][0] * (d[1][1] * d[2][2] - d[1][2] * d[2][1]) -
+        d[0][1] * (d[1][0] * d[2][2] - d[1][2] * d[2][0]) +
+        d[0][2] * (d[1][0] * d[2][1] - d[1][1] * d[2][0])
+    }
+
+    pub fn inverse(&self) -> Option<Mat3> {
+        let det = self.determinant();
+        if det.abs() < 1e-12 {
+            return None;
+        }
+        let d = &self.data;
+        let mut inv = [[0.0; 3]; 3];
+        for (i, row) in inv.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                // cofactor of the transposed position, ie the adjugate
+                let (a, b) = ((j + 1) % 3, (j + 2) % 3);
+                let (c, e) = ((i + 1) % 3, (i + 2) % 3);
+                *value = (d[a][c] * d[b][e] - d[a][e] * d[b][c]) / det;
+            }
+        }
+        Some(Mat3 { data: inv })
+    }
+}
+
+impl Quaternion {
+    pub fn new(w: f64, x: f64, y: f64, z: f64) -> Quaternion {
+        Quaternion { w, x, y, z }
+    }
+
+    pub fn identity() -> Quaternion {
+        Quaternion::new(1.0, 0.0, 0.0, 0.0)
+    }
+
+    pub fn from_axis_angle(axis: &Vec3, angle: f64) -> Quaternion {
+        // angle in radians, right handed about the axis
+        let axis = match axis.unit_vector() {
+            Some(axis) => axis,
+            None => return Quaternion::identity(),
+        };
+        let half = angle / 2.0;
+        Quaternion::new(half.cos(), axis.x * half.sin(), axis.y * half.sin(), axis.z * half.sin())
+    }
+
+    pub fn from_angles(angles: &Angles) -> Quaternion {
+        /*
+        yaw about world z, then pitch, then roll. nose up pitch and left wing down roll
+        are both negative rotations about the body axes because body y points left
+         */
+        let yaw = Quaternion::from_axis_angle(&Vec3::new(0.0, 0.0, 1.0), deg_to_rad(angles.azimouth));
+        let pitch = Quaternion::from_axis_angle(&Vec3::new(0.0, 1.0, 0.0), -deg_to_rad(angles.altitude));
+        let roll = Quaternion::from_axis_angle(&Vec3::new(1.0, 0.0, 0.0), -deg_to_rad(angles.roll));
+        yaw * &pitch * &roll
+    }
+
+    pub fn as_angles(&self) -> Angles {
+        // pull the euler angles back out, for the camera and the logs
+        let forwards = self.rotate(&Vec3::new(1.0, 0.0, 0.0));
+        let left = self.rotate(&Vec3::new(0.0, 1.0, 0.0));
+        let up = self.rotate(&Vec3::new(0.0, 0.0, 1.0));
+        Angles {
+            azimouth: rad_to_deg(forwards.y.atan2(forwards.x)),
+            altitude: rad_to_deg(forwards.z.clamp(-1.0, 1.0).asin()),
+            roll: rad_to_deg((-left.z).atan2(up.z)),
+        }
+    }
+
+    pub fn magnitude(&self) -> f64 {
+        (self.w.powf(2.0) + self.x.powf(2.0) + self.y.powf(2.0) + self.z.powf(2.0)).powf(0.5)
+    }
+
+    pub fn normalised(&self) -> Quaternion {
+        let mag = self.magnitude();
+        Quaternion::new(self.w / mag, self.x / mag, self.y / mag, self.z / mag)
+    }
+
+    pub fn conjugate(&self) -> Quaternion {
+        Quaternion::new(self.w, -self.x, -self.y, -self.z)
+    }
+
+    pub fn rotate(&self, other: &Vec3) -> Vec3 {
+        // body coords into world coords
+        let v = Quaternion::new(0.0, other.x, other.y, other.z);
+        let rotated = *self * &v * &self.conjugate();
+        Vec3::new(rotated.x, rotated.y, rotated.z)
+    }
+
+    pub fn rotate_inverse(&self, other: &Vec3) -> Vec3 {
+        // world coords into body coords
+        self.conjugate().rotate(other)
+    }
+
+    pub fn derivative(&self, body_rates: &Vec3) -> Quaternion {
+        // dq/dt = 0.5 * q * (0, w)
+        let rates = Quaternion::new(0.0, body_rates.x, body_rates.y, body_rates.z);
+        (*self * &rates) * 0.5
+    }
+
+    pub fn integrate(&self, body_rates: &Vec3, dt: f64) -> Quaternion {
+        // one euler step, renormalised so it stays a rotation
+        (*self + &(self.derivative(body_rates) * dt)).normalised()
+    }
+
+    pub fn slerp(&self, other: &Quaternion, fraction: f64) -> Quaternion {
+        // rotate a fraction of the way from self to other, the short way round
+        let mut cos_half = self.w * other.w + self.x * other.x + self.y * other.y + self.z * other.z;
+        let other = if cos_half < 0.0 {
+            cos_half = -cos_half;
+            *other * -1.0
+        } else {
+            *other
+        };
+        if cos_half > 0.9995 {
+            // too close together to divide by sin, a straight line is fine
+            return (*self * (1.0 - fraction) + &(other * fraction)).normalised();
+        }
+        let half = cos_half.acos();
+        let from = ((1.0 - fraction) * half).sin() / half.sin();
+        let to = (fraction * half).sin() / half.sin();
+        (*self * from + &(other * to)).normalised()
+    }
+
+    pub fn jsonify(&self) -> String {
+        format!("{{\"w\": {}, \"x\": {}, \"y\": {}, \"z\": {}}}", self.w, self.x, self.y, self.z)
+    }
+}
+
+impl Angles {
+    pub fn new(azimouth: f64, altitude: f64, roll: f64) -> Angles {
+        Angles {
+            azimouth,
+            altitude,
+            roll,
+        }
+    }
+    
+    #[allow(dead_code)]
+    pub fn as_vec3(&self) -> Vec3 {
+        let x_azimouth = deg_to_rad(self.azimouth).cos();
+        let x_altitude = x_azimouth * deg_to_rad(self.altitude).cos();
+
+        let y_azimouth = deg_to_rad(self.azimouth).sin();
+        let y_altitude = y_azimouth * deg_to_rad(self.altitude).cos();
+        
+        let z_altitude = deg_to_rad(self.altitude).sin();
+
+        let x = x_altitude; 
+        let y = y_altitude;
+        let z = z_altitude;
+        Vec3 {x, y, z}
+    }
+    
+    pub fn jsonify(&self) -> String {
+        format!("{{\"alt\": {}, \"az\": {}, \"roll\": {}}}", self.altitude, self.azimouth, self.roll)
+    }
+}
+
+impl From<[f64; 3]> for Vec3 {
+    fn from(components: [f64; 3]) -> Vec3 {
+        Vec3::new(components[0], components[1], components[2])
+    }
+}
+
+impl std::ops::Add<&Vec3> for Vec3 {
+    type Output = Vec3;
+    fn add(self, other: &Vec3) -> Vec3 {
+        Vec3 {
+            x: self.x + other.x,
+            y: self.y + other.y,
+            z: self.z + other.z,
+        }
+    }
+}
+
+impl std::ops::Sub<&Vec3> for Vec3 {
+    type Output = Vec3;
+    fn sub(self, other: &Vec3) -> Vec3 {
+        Vec3 {
+            x: self.x - other.x,
+            y: self.y - other.y,
+            z: self.z - other.z,
+        }
+    }
+}
+
+impl std::ops::Mul<f64> for Vec3 {
+    type Output = Vec3;
+    fn mul(self, other: f64) -> Vec3 {
+        Vec3{
+            x: self.x * other,
+            y: self.y * other,
+            z: self.z * other,
+        }
+    }
+}
+
+impl std::ops::Mul<&Quaternion> for Quaternion {
+    type Output = Quaternion;
+    fn mul(self, other: &Quaternion) -> Quaternion {
+        Quaternion {
+            w: self.w * other.w - self.x * other.x - self.y * other.y - self.z * other.z,
+            x: self.w * other.x + self.x * other.w + self.y * other.z - self.z * other.y,
+            y: self.w * other.y - self.x * other.z + self.y * other.w + self.z * other.x,
+            z: self.w * other.z + self.x * other.y - self.y * other.x + self.z * other.w,
+        }
+    }
+}
+
+impl std::ops::Add<&Quaternion> for Quaternion {
+    type Output = Quaternion;
+    fn add(self, other: &Quaternion) -> Quaternion {
+        Quaternion::new(self.w + other.w, self.x + other.x, self.y + other.y, self.z + other.z)
+    }
+}
+
+impl std::ops::Mul<f64> for Quaternion {
+    type Output = Quaternion;
+    fn mul(self, other: f64) -> Quaternion {
+        Quaternion::new(self.w * other, self.x * other, self.y * other, self.z * other)
+    }
+}
+
+impl std::ops::Add<&Angles> for Angles {
+    type Output = Angles;
+    fn add(self, other: &Angles) -> Angles {
+        Angles {
+            azimouth: self.azimouth + other.azimouth,
+            altitude: self.altitude + other.altitude,
+            roll: self.roll + other.roll,
+        }
+    }
+}
+
+impl std::ops::Mul<f64> for Angles {
+    type Output = Angles;
+    fn mul(self, other: f64) -> Angles {
+        Angles {
+            altitude: self.altitude * other,
+            azimouth: self.azimouth * other,
+            roll: self.roll * other,
+        }
+    }
+}
+
+impl PartialEq for Vec3 {
+    fn eq(&self, other: &Self) -> bool {
+        (self.x - other.x).abs() < 1e-10 && 
+        (self.y - other.y).abs() < 1e-10 && 
+        (self.z - other.z).abs() < 1e-10 
+    }
+}
+
+#[allow(dead_code)]
+pub fn rad_to_deg(radians: f64) -> f64 {
+    radians * 180.0 / std::f64::consts::PI
+}
+
+pub fn deg_to_rad(degrees: f64) -> f64 {
+    degrees * std::f64::consts::PI / 180.0  
+}
+
+pub fn interpolate(table: &[(f64, f64)], x: f64) -> f64 {
+    // straight lines between the points, sticks at the end values outside the table
+    if x <= table[0].0 {
+        return table[0].1;
+    }
+    for pair in table.windows(2) {
+        let (x0, y0) = pair[0];
+        let (x1, y1) = pair[1];
+        if x <= x1 {
+            return y0 + (y1 - y0) * (x - x0) / (x1 - x0);
+        }
+    }
+    table[table.len() - 1].1
+}
+
+#[cfg(test)]
+mod test{
+    use core::f64;
+
+    use super::*;
+    #[test]
+    fn deg_to_rad_correct() {
+        assert_eq!(deg_to_rad(180.0), std::f64::consts::PI);
+    }
+
+    #[test]
+    fn magnitude_correct() {
+        let thing = Vec3::new(2.0, 3.0, 4.0);
+        assert_eq!(thing.magnitude(), 29_f64.sqrt())
+    }
+
+    #[test]
+    fn unit_vector_correct() {
+        //TODO regression
+    }
+
+    #[test]
+    fn coordinate_transform_correct() {
+        // case 1: azimouthed 90 degrees to right, thrust pointing forwards
+        let test_transform_1 = Vec3::new(1.0, 0.0, 0.0);
+        let pointing_1 = Angles::new(90.0, 0.0, 0.0);
+        let answer_1 = Vec3::new(0.0, 1.0, 0.0); 
+        let check_1 = test_transform_1.transform_coordinates(&pointing_1);
+        assert!((answer_1.x - check_1.x).abs() < 1e-10);
+        assert!((answer_1.y - check_1.y).abs() < 1e-10);
+        assert!((answer_1.z - check_1.z).abs() < 1e-10);
+        assert!((answer_1.magnitude() - 1.0).abs() < 1e-10);
+
+        // case 2: azimouthed 90 degrees to left, thrust pointing forwards
+        let test_transform_2 = Vec3::new(1.0, 0.0, 0.0);
+        let pointing_2 = Angles::new(-90.0, 0.0, 0.0);
+        let answer_2 = Vec3::new(0.0, -1.0, 0.0); 
+        let check_2 = test_transform_2.transform_coordinates(&pointing_2);
+        assert!((answer_2.x - check_2.x).abs() < 1e-10);
+        assert!((answer_2.y - check_2.y).abs() < 1e-10);
+        assert!((answer_2.z - check_2.z).abs() < 1e-10);
+        assert!((answer_2.magnitude() - 1.0).abs() < 1e-10);
+        
+        // case 3: azimouthed 90 degrees to right, thrust pointing right
+        let test_transform_3 = Vec3::new(0.0, 1.0, 0.0);
+        let pointing_3 = Angles::new(90.0, 0.0, 0.0);
+        let answer_3 = Vec3::new(-1.0, 0.0, 0.0); 
+        let check_3 = test_transform_3.transform_coordinates(&pointing_3);
+        assert!((answer_3.x - check_3.x).abs() < 1e-10);
+        assert!((answer_3.y - check_3.y).abs() < 1e-10);
+        assert!((answer_3.z - check_3.z).abs() < 1e-10);
+        assert!((answer_3.magnitude() - 1.0).abs() < 1e-10);
+
+        // case 4: azimouthed 90 degrees to the left, thrust pointing right
+        let test_transform_4 = Vec3::new(0.0, 1.0, 0.0);
+        let pointing_4 = Angles::new(-90.0, 0.0, 0.0);
+        let answer_4 = Vec3::new(1.0, 0.0, 0.0); 
+        let check_4 = test_transform_4.transform_coordinates(&pointing_4);
+        assert!((answer_4.x - check_4.x).abs() < 1e-10);
+        assert!((answer_4.y - check_4.y).abs() < 1e-10);
+        assert!((answer_4.z - check_4.z).abs() < 1e-10);
+        assert!((answer_4.magnitude() - 1.0).abs() < 1e-10);
+
+        // case 5: altitude 45 degrees up and azimouth 45 degrees to the right, thrust pointing forwards
+        let test_transform_5 = Vec3::new(1.0, 0.0, 0.0);
+        let pointing_5 = Angles::new(45.0, 45.0, 0.0);
+        let answer_5 = Vec3::new(1.0 / 2.0_f64.sqrt() * 1.0 / 2.0_f64.sqrt(), 1.0 / 2.0_f64.sqrt() * 1.0 / 2.0_f64.sqrt(), 1.0 / 2.0_f64.sqrt()); 
+        let check_5 = test_transform_5.transform_coordinates(&pointing_5);
+        assert!((answer_5.x - check_5.x).abs() < 1e-10);
+        assert!((answer_5.y - check_5.y).abs() < 1e-10);
+        assert!((answer_5.z - check_5.z).abs() < 1e-10);
+        assert!((answer_5.magnitude() - 1.0).abs() < 1e-10);
+        
+        // case 6: altitude 10 degrees up and roll 10 degrees left
+        let test_transform_6 = Vec3::new(1.0, 0.0, 0.0);
+        let pointing_6 = Angles::new(0.0, 10.0, 10.0);
+        let answer_6 = Vec3::new(deg_to_rad(10.0).cos(), 0.0, deg_to_rad(10.0).sin()); 
+        let check_6 = test_transform_6.transform_coordinates(&pointing_6);
+        assert!((answer_6.x - check_6.x).abs() < 1e-10);
+        assert!((answer_6.y - check_6.y).abs() < 1e-10);
+        assert!((answer_6.z - check_6.z).abs() < 1e-10);
+        assert!((answer_6.magnitude() - 1.0).abs() < 1e-10);
+
+        // case 7
+        let test_transform_7 = Vec3::new(1.0, 0.0, 0.0);
+        let pointing_7 = Angles::new(0.0, -10.0, 0.0);
+        let answer_7 = Vec3::new(deg_to_rad(-10.0).cos(), 0.0, deg_to_rad(-10.0).sin()); 
+        let check_7 = test_transform_7.transform_coordinates(&pointing_7);
+        assert!((answer_7.x - check_7.x).abs() < 1e-10);
+        assert!((answer_7.y - check_7.y).abs() < 1e-10);
+        assert!((answer_7.z - check_7.z).abs() < 1e-10);
+        assert!((answer_7.magnitude() - 1.0).abs() < 1e-10);
+
+        // case 8
+        let test_transform_8 = Vec3::new(0.0, 0.0, 1.0);
+        let pointing_8 = Angles::new(0.0, 0.0, 90.0);
+        let answer_8 = Vec3::new(0.0, 1.0, 0.0); 
+        let check_8 = test_transform_8.transform_coordinates(&pointing_8);
+        assert!((answer_8.x - check_8.x).abs() < 1e-10);
+        assert!((answer_8.y - check_8.y).abs() < 1e-10);
+        assert!((answer_8.z - check_8.z).abs() < 1e-10);
+        assert!((answer_8.magnitude() - 1.0).abs() < 1e-10);
+        //TODO extend test suite
+    }
+
+    #[test]
+    fn test_angle_with() {
+        let vec1_1 = Vec3::new(0.0, 1.0, 0.0);
+        let vec1_2 = Vec3::new(0.0, -1.0, 0.0);
+        println!("{}", vec1_1.angle_with(&vec1_2)/f64::consts::PI);
+        let vec2_1 = Vec3::new(1.0, 0.0, 0.0);
+        println!("{}", vec1_1.angle_with(&vec2_1)/f64::consts::PI);
+        let vec2_2 = Vec3::new(1.0, 0.0, 1.0).unit_vector().unwrap();
+        println!("{}, {:?}", vec2_1.angle_with(&vec2_2)/f64::consts::PI, vec2_2)
+    }
+
+    #[test]
+    fn cross_product_correct() {
+        let x = Vec3::new(1.0, 0.0, 0.0);
+        let y = Vec3::new(0.0, 1.0, 0.0);
+        assert_eq!(x.cross_product(&y), Vec3::new(0.0, 0.0, 1.0));
+        assert_eq!(y.cross_product(&x), Vec3::new(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn inverse_correct() {
+        let inertia = Mat3::inertia(1285.0, 1825.0, 2667.0, 100.0);
+        let inverse = inertia.inverse().unwrap();
+        let thing = Vec3::new(1.0, -2.0, 3.0);
+        assert_eq!(inverse.mul_vec(&inertia.mul_vec(&thing)), thing);
+
+        assert!(Mat3::new([[1.0, 2.0, 3.0], [2.0, 4.0, 6.0], [0.0, 0.0, 1.0]]).inverse().is_none());
+    }
+
+    #[test]
+    fn quaternion_matches_transform() {
+        // where the old transform is right, the quaternion should agree with it
+        let cases = [
+            (Vec3::new(1.0, 0.0, 0.0), Angles::new(90.0, 0.0, 0.0)),
+            (Vec3::new(0.0, 1.0, 0.0), Angles::new(-90.0, 0.0, 0.0)),
+            (Vec3::new(1.0, 0.0, 0.0), Angles::new(45.0, 45.0, 0.0)),
+            (Vec3::new(1.0, 0.0, 0.0), Angles::new(0.0, 10.0, 10.0)),
+            (Vec3::new(0.0, 0.0, 1.0), Angles::new(0.0, 0.0, 90.0)),
+        ];
+        for (vector, pointing) in cases {
+            let rotated = Quaternion::from_angles(&pointing).rotate(&vector);
+            assert_eq!(rotated, vector.transform_coordinates(&pointing));
+            assert_eq!(Quaternion::from_angles(&pointing).rotate_inverse(&rotated), vector);
+        }
+    }
+
+    #[test]
+    fn quaternion_angles_round_trip() {
+        for pointing in [Angles::new(30.0, 20.0, -45.0), Angles::new(-120.0, -60.0, 170.0), Angles::new(0.0, 89.0, 0.0)] {
+            let back = Quaternion::from_angles(&pointing).as_angles();
+            assert!((back.azimouth - pointing.azimouth).abs() < 1e-8);
+            assert!((back.altitude - pointing.altitude).abs() < 1e-8);
+            assert!((back.roll - pointing.roll).abs() < 1e-8);
+        }
+    }
+
+    #[test]
+    fn loop_the_loop() {
+        // pitch all the way round through vertical and end up back where we started
+        let mut attitude = Quaternion::identity();
+        let steps = 10000;
+        let dt = 2.0 * f64::consts::PI / steps as f64;
+        for i in 0..steps {
+            attitude = attitude.integrate(&Vec3::new(0.0, -1.0, 0.0), dt);
+            if i == steps / 4 {
+                // a quarter of the way round should be pointing straight up
+                assert!((attitude.rotate(&Vec3::new(1.0, 0.0, 0.0)).z - 1.0).abs() < 1e-3);
+            }
+        }
+        let forwards = attitude.rotate(&Vec3::new(1.0, 0.0, 0.0));
+        assert!((forwards.x - 1.0).abs() < 1e-3);
+        assert!((attitude.magnitude() - 1.0).abs() < 1e-10);
+    }
+
+    #[test]
+    fn slerp_halfway() {
+        let level = Quaternion::identity();
+        let banked = Quaternion::from_angles(&Angles::new(0.0, 0.0, 60.0));
+        let halfway = level.slerp(&banked, 0.5).as_angles();
+        assert!((halfway.roll - 30.0).abs() < 1e-9);
+        assert!((level.slerp(&banked, 1.0).as_angles().roll - 60.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn interpolate_correct() {
+        let table = [(0.0, 1.0), (1.0, 3.0), (3.0, -1.0)];
+        assert_eq!(interpolate(&table, -1.0), 1.0);
+        assert_eq!(interpolate(&table, 0.5), 2.0);
+        assert_eq!(interpolate(&table, 2.0), 1.0);
+        assert_eq!(interpolate(&table, 10.0), -1.0);
+    }
+
+    #[test]
+    fn test_angle_to_vec3() {
+        assert_eq!(Angles::new(0.0, 0.0, 0.0).as_vec3(), 
+            Vec3::new(1.0, 0.0, 0.0)
+        );
+
+        assert_eq!(Angles::new(0.0, 90.0, 0.0).as_vec3(), 
+            Vec3::new(0.0, 0.0, 1.0)
+        );
+
+        assert_eq!(Angles::new(90.0, 0.0, 0.0).as_vec3(), 
+            Vec3::new(0.0, 1.0, 0.0)
+        );
+    }
 }
\ No newline at end of file
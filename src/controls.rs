@@ -0,0 +1,136 @@
+use crate::common_math::deg_to_rad;
+
+// travel limits from the cessna POH, in degrees
+const ELEVATOR_UP: f64 = 28.0;
+const ELEVATOR_DOWN: f64 = 23.0;
+const AILERON_TRAVEL: f64 = 20.0;
+pub const RUDDER_TRAVEL: f64 = 16.0;
+const TRIM_TRAVEL: f64 = 20.0; // the tab on the elevator, each way
+const TRIM_POWER: f64 = 0.5; // degrees of elevator the tab holds the elevator at per degree of tab
+
+pub fn elevator_up(angle: f64) -> f64 {
+    // how far back the elevator is as a fraction of the travel, nothing if its forward
+    (angle / deg_to_rad(ELEVATOR_UP)).max(0.0)
+}
+
+pub struct ControlSurfaces {
+    // all in radians. positive is whatever gives nose up, left wing down and nose left
+    pub elevator: f64,
+    pub aileron: f64,
+    pub rudder: f64,
+    pub trim: f64, // the trim tab, positive is nose up like the elevator
+}
+
+impl ControlSurfaces {
+    pub fn new() -> ControlSurfaces {
+        ControlSurfaces {
+            elevator: 0.0,
+            aileron: 0.0,
+            rudder: 0.0,
+            trim: 0.0,
+        }
+    }
+
+    // these move the surfaces by some degrees, stopping at the end of their travel
+    pub fn elevator_by(&mut self, amount: f64) {
+        self.elevator = (self.elevator + deg_to_rad(amount)).clamp(-deg_to_rad(ELEVATOR_DOWN), deg_to_rad(ELEVATOR_UP));
+    }
+
+    pub fn aileron_by(&mut self, amount: f64) {
+        self.aileron = (self.aileron + deg_to_rad(amount)).clamp(-deg_to_rad(AILERON_TRAVEL), deg_to_rad(AILERON_TRAVEL));
+    }
+
+    pub fn rudder_by(&mut self, amount: f64) {
+        self.rudder = (self.rudder + deg_to_rad(amount)).clamp(-deg_to_rad(RUDDER_TRAVEL), deg_to_rad(RUDDER_TRAVEL));
+    }
+
+    pub fn trim_by(&mut self, amount: f64) {
+        self.trim = (self.trim + deg_to_rad(amount)).clamp(-deg_to_rad(TRIM_TRAVEL), deg_to_rad(TRIM_TRAVEL));
+    }
+
+    pub fn elevator_angle(&self) -> f64 {
+        /*
+        where the elevator actually sits. the tab floats it up or down on its own so the stick
+        only has to do whats left over, and its still stopped by the elevators own travel
+         */
+        (self.elevator + TRIM_POWER * self.trim).clamp(-deg_to_rad(ELEVATOR_DOWN), deg_to_rad(ELEVATOR_UP))
+    }
+
+    pub fn hold_elevator(&mut self, angle: f64) {
+        // trim out as much of it as the tab can take and hold the rest on the stick
+        self.trim = (angle / TRIM_POWER).clamp(-deg_to_rad(TRIM_TRAVEL), deg_to_rad(TRIM_TRAVEL));
+        self.elevator = angle - TRIM_POWER * self.trim;
+    }
+
+    pub fn out_of_travel(elevator: f64, aileron: f64, rudder: f64) -> Option<&'static str> {
+        // which surface, if any, cant get to the angle wanted, for something working out where they should be
+        let reaches = |angle: f64, down: f64, up: f64| (-deg_to_rad(down)..=deg_to_rad(up)).contains(&angle);
+        if !reaches(elevator, ELEVATOR_DOWN, ELEVATOR_UP) {
+            Some("elevator")
+        } else if !reaches(aileron, AILERON_TRAVEL, AILERON_TRAVEL) {
+            Some("aileron")
+        } else if !reaches(rudder, RUDDER_TRAVEL, RUDDER_TRAVEL) {
+            Some("rudder")
+        } else {
+            None
+        }
+    }
+
+    // when the keys are let go the ailerons and rudder get blown back to the middle
+    pub fn centre_aileron(&mut self, amount: f64) {
+        self.aileron = centre(self.aileron, deg_to_rad(amount));
+    }
+
+    pub fn centre_rudder(&mut self, amount: f64) {
+        self.rudder = centre(self.rudder, deg_to_rad(amount));
+    }
+}
+
+fn centre(deflection: f64, amount: f64) -> f64 {
+    if deflection.abs() <= amount {
+        return 0.0;
+    }
+    deflection - amount * deflection.signum()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn stops_at_limits() {
+        let mut controls = ControlSurfaces::new();
+        controls.elevator_by(100.0);
+        assert_eq!(controls.elevator, deg_to_rad(ELEVATOR_UP));
+        controls.elevator_by(-200.0);
+        assert_eq!(controls.elevator, -deg_to_rad(ELEVATOR_DOWN));
+        controls.rudder_by(-50.0);
+        assert_eq!(controls.rudder, -deg_to_rad(RUDDER_TRAVEL));
+    }
+
+    #[test]
+    fn centres_without_overshooting() {
+        let mut controls = ControlSurfaces::new();
+        controls.aileron_by(5.0);
+        controls.centre_aileron(3.0);
+        assert!((controls.aileron - deg_to_rad(2.0)).abs() < 1e-10);
+        controls.centre_aileron(3.0);
+        assert_eq!(controls.aileron, 0.0);
+    }
+
+    #[test]
+    fn trim_holds_the_elevator() {
+        let mut controls = ControlSurfaces::new();
+        controls.trim_by(10.0);
+        assert!((controls.elevator_angle() - deg_to_rad(10.0 * TRIM_POWER)).abs() < 1e-10);
+        controls.trim_by(100.0);
+        assert_eq!(controls.trim, deg_to_rad(TRIM_TRAVEL));
+
+        // more than the tab can hold ends up on the stick
+        controls.hold_elevator(deg_to_rad(15.0));
+        assert_eq!(controls.trim, deg_to_rad(TRIM_TRAVEL));
+        assert!((controls.elevator_angle() - deg_to_rad(15.0)).abs() < 1e-10);
+        assert_eq!(ControlSurfaces::out_of_travel(deg_to_rad(30.0), 0.0, 0.0), Some("elevator"));
+        assert_eq!(ControlSurfaces::out_of_travel(0.0, 0.0, 0.1), None);
+    }
+}
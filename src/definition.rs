@@ -0,0 +1,192 @@
+use serde::Deserialize;
+
+use crate::common_math::Vec3;
+use crate::aero::{AeroModel, DerivativeModel, StabilityDerivatives, WingAndFin};
+use crate::touchdown::Contact;
+use crate::{balance, engine, fin, gear, jsbsim, propeller, wing};
+
+// the default plane gets built in so theres always something to fly
+const CESSNA: &str = include_str!("../aircraft/c172.toml");
+
+// everything that makes one plane different from another, loaded from a toml file
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct AircraftDefinition {
+    pub name: String,
+    pub geometry: Geometry,
+    pub mass: MassProperties,
+    #[serde(default)]
+    pub balance: balance::Balance,
+    pub aero: AeroCoefficients,
+    #[serde(default)]
+    pub derivatives: Option<StabilityDerivatives>, // a whole set of stability derivatives, what it flies on if its got them
+    pub wing: wing::WingDefinition,
+    pub fin: fin::Fin,
+    pub propeller: propeller::Propeller,
+    pub engine: engine::EngineDefinition,
+    pub gear: gear::Gear,
+    #[serde(skip)]
+    pub aero_tables: Option<jsbsim::Aerodynamics>, // a jsbsim plane brings its own aero instead of the coefficients
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Geometry {
+    pub area: f64, // m^2
+    pub chord: f64, // m
+    pub span: f64, // m
+    pub thrust_position: Vec3, // where the propeller is relative to the reference point
+    #[serde(default)]
+    pub wing_position: Vec3, // the middle of the wing, its height off the ground is what the ground effect goes on
+    #[serde(default)]
+    pub contacts: Vec<Contact>, // wingtips, tail, prop and belly, whatever crashes it if it hits the ground
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct MassProperties {
+    // the empty plane, the balance adds the people, bags and fuel on top
+    pub mass: f64, // kg
+    #[serde(default)]
+    pub cg: Vec3, // from the reference point in body coords
+    pub inertia: [f64; 4], // ixx, iyy, izz, ixz in kg m^2 about its own CG
+}
+
+// these use the textbook axes (x forward, y right, z down), see the toml for the signs
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct AeroCoefficients {
+    pub cm_0: f64,
+    pub cm_alpha: f64,
+    pub cm_q: f64,
+    pub croll_p: f64,
+    pub croll_r: f64,
+    pub croll_beta: f64,
+    pub cyaw_r: f64,
+    pub cyaw_p: f64,
+    pub cside_beta: f64,
+    pub cm_elevator: f64,
+    pub clift_elevator: f64,
+    pub croll_aileron: f64,
+    pub cyaw_aileron: f64,
+    #[serde(default)]
+    pub croll_p_stalled: f64, // roll damping once the wing has let go, the down going side stalls deeper so it can even drive the roll
+}
+
+impl AircraftDefinition {
+    pub fn cessna() -> AircraftDefinition {
+        AircraftDefinition::parse(CESSNA).expect("the built in 172 should always load")
+    }
+
+    pub fn load(path: &str) -> Result<AircraftDefinition, String> {
+        if path.ends_with(".xml") {
+            return jsbsim::load(path);
+        }
+        let text = std::fs::read_to_string(path).map_err(|error| format!("couldnt read {}: {}", path, error))?;
+        AircraftDefinition::parse(&text).map_err(|error| format!("{}: {}", path, error))
+    }
+
+    pub fn parse(text: &str) -> Result<AircraftDefinition, String> {
+        let definition: AircraftDefinition = toml::from_str(text).map_err(|error| error.to_string())?;
+        definition.check()?;
+        Ok(definition)
+    }
+
+    pub fn aero_model(&self, derivatives: bool) -> Box<dyn AeroModel> {
+        // the derivatives if theyre wanted and there are some, then the jsbsim tables, otherwise the wing and fin
+        match (&self.derivatives, &self.aero_tables) {
+            (Some(set), _) if derivatives => Box::new(DerivativeModel::new(set, &self.geometry)),
+            (_, Some(tables)) => Box::new(tables.clone()),
+            _ => Box::new(WingAndFin::new(self)),
+        }
+    }
+
+    pub fn check(&self) -> Result<(), String> {
+        // whichever file it came from it still has to be something that can fly
+        let geometry = &self.geometry;
+        if self.mass.mass <= 0.0 || geometry.area <= 0.0 || geometry.span <= 0.0 || geometry.chord <= 0.0 {
+            return Err("mass, area, span and chord all have to be more than 0".to_string());
+        }
+        if self.gear.legs.is_empty() {
+            return Err("it needs at least one gear leg to sit on".to_string());
+        }
+        // the loading only adds point masses on top, so if the empty plane can be inverted so can the loaded one
+        let [ixx, iyy, izz, ixz] = self.mass.inertia;
+        if ixx <= 0.0 || iyy <= 0.0 || izz <= 0.0 || ixx * izz - ixz * ixz <= 0.0 {
+            return Err("the inertia has to be positive definite, ixx, iyy and izz over 0 and ixz squared under ixx times izz".to_string());
+        }
+        table("propeller thrust_coefficient", &self.propeller.thrust_coefficient)?;
+        table("propeller power_coefficient", &self.propeller.power_coefficient)?;
+        table("wing attached_lift", &self.wing.attached_lift)?;
+        table("wing separated_lift", &self.wing.separated_lift)?;
+        Ok(())
+    }
+}
+
+fn table(name: &str, table: &[(f64, f64)]) -> Result<(), String> {
+    // the interpolation needs something in there, going up left to right
+    if table.is_empty() {
+        return Err(format!("{} is empty", name));
+    }
+    if table.windows(2).any(|pair| pair[1].0 <= pair[0].0) {
+        return Err(format!("{} has to go up in order along the left hand column", name));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cessna_loads() {
+        let definition = AircraftDefinition::cessna();
+        assert_eq!(definition.name, "cessna 172S");
+        assert_eq!(definition.mass.mass, 767.0);
+        assert_eq!(definition.balance.stations[0].name, "pilot");
+        assert_eq!(definition.gear.legs.len(), 3);
+        assert_eq!(definition.gear.legs[0].max_steer, 10.0);
+        assert_eq!(definition.geometry.contacts.len(), 5);
+        assert_eq!(definition.propeller.thrust_coefficient[0], (0.0, 0.105));
+        // and the file on disk is the same one
+        assert_eq!(AircraftDefinition::load("aircraft/c172.toml").unwrap().geometry.span, definition.geometry.span);
+    }
+
+    #[test]
+    fn complains_about_bad_files() {
+        assert!(AircraftDefinition::load("aircraft/not_a_plane.toml").is_err());
+        // a typo should get caught rather than quietly using nothing
+        let typo = CESSNA.replace("cm_alpha", "cm_alhpa");
+        assert!(AircraftDefinition::parse(&typo).is_err());
+        let weightless = CESSNA.replace("mass = 767.0", "mass = 0.0");
+        assert!(AircraftDefinition::parse(&weightless).is_err());
+    }
+
+    fn problem(text: &str) -> String {
+        AircraftDefinition::parse(text).err().expect("that should have been turned down")
+    }
+
+    #[test]
+    fn inertia_has_to_invert() {
+        let flat = CESSNA.replace("inertia = [842.0, 1663.0, 2273.0, 22.0]", "inertia = [0.0, 0.0, 0.0, 0.0]");
+        assert!(problem(&flat).contains("inertia"));
+        // all positive but the cross term makes it singular
+        let skewed = CESSNA.replace("inertia = [842.0, 1663.0, 2273.0, 22.0]", "inertia = [842.0, 1663.0, 2273.0, 1400.0]");
+        assert!(problem(&skewed).contains("inertia"));
+    }
+
+    #[test]
+    fn tables_have_to_be_filled_and_in_order() {
+        let mut empty = AircraftDefinition::cessna();
+        empty.propeller.power_coefficient.clear();
+        assert!(empty.check().err().unwrap().contains("power_coefficient is empty"));
+        let mut empty = AircraftDefinition::cessna();
+        empty.wing.separated_lift.clear();
+        assert!(empty.check().err().unwrap().contains("separated_lift is empty"));
+
+        let backwards = CESSNA.replace("[0.0, 0.105], [0.2, 0.098]", "[0.2, 0.105], [0.0, 0.098]");
+        assert!(problem(&backwards).contains("thrust_coefficient"));
+        let repeated = CESSNA.replace("[11.0, 1.1], [14.0, 1.25]", "[11.0, 1.1], [11.0, 1.25]");
+        assert!(problem(&repeated).contains("attached_lift"));
+    }
+}
@@ -0,0 +1,303 @@
+// eigenvalues and eigenvectors of a small real matrix, enough for the linearised plane
+
+const MAX_ITERATIONS: usize = 60; // per eigenvalue, it normally takes a handful
+const INVERSE_ITERATIONS: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Complex {
+    pub re: f64,
+    pub im: f64,
+}
+
+impl Complex {
+    pub fn new(re: f64, im: f64) -> Complex {
+        Complex { re, im }
+    }
+
+    pub fn magnitude(&self) -> f64 {
+        self.re.hypot(self.im)
+    }
+
+    fn conjugate(&self) -> Complex {
+        Complex::new(self.re, -self.im)
+    }
+}
+
+impl std::ops::Add for Complex {
+    type Output = Complex;
+    fn add(self, other: Complex) -> Complex {
+        Complex::new(self.re + other.re, self.im + other.im)
+    }
+}
+
+impl std::ops::Sub for Complex {
+    type Output = Complex;
+    fn sub(self, other: Complex) -> Complex {
+        Complex::new(self.re - other.re, self.im - other.im)
+    }
+}
+
+impl std::ops::Mul for Complex {
+    type Output = Complex;
+    fn mul(self, other: Complex) -> Complex {
+        Complex::new(self.re * other.re - self.im * other.im, self.re * other.im + self.im * other.re)
+    }
+}
+
+impl std::ops::Div for Complex {
+    type Output = Complex;
+    fn div(self, other: Complex) -> Complex {
+        let size = other.re * other.re + other.im * other.im;
+        let top = self * other.conjugate();
+        Complex::new(top.re / size, top.im / size)
+    }
+}
+
+pub fn eigenvalues(matrix: &[Vec<f64>]) -> Option<Vec<Complex>> {
+    /*
+    householder reflections take it down to upper hessenberg, then double shift QR steps chip the
+    eigenvalues off the bottom corner one or two at a time. the step is done the long way, building
+    (H - a)(H - b) for the two shifts out of the bottom 2x2 and taking its QR, which is plenty quick
+    for an 8x8. the pairs come out of a 2x2 so complex ones are exact conjugates and real ones have no
+    imaginary part at all. None if it wont settle
+     */
+    let mut h = matrix.to_vec();
+    let mut size = h.len();
+    hessenberg(&mut h, size);
+    let mut values = Vec::with_capacity(size);
+    let mut iterations = 0;
+    while size > 0 {
+        let last = size - 1;
+        if size == 1 || split(&mut h, last) {
+            values.push(Complex::new(h[last][last], 0.0));
+            size -= 1;
+            iterations = 0;
+            continue;
+        }
+        if size == 2 || split(&mut h, last - 1) {
+            values.extend(corner(&h, last - 1));
+            size -= 2;
+            iterations = 0;
+            continue;
+        }
+        if iterations == MAX_ITERATIONS {
+            return None;
+        }
+        iterations += 1;
+        let (a, b, c, d) = (h[last - 1][last - 1], h[last - 1][last], h[last][last - 1], h[last][last]);
+        let (sum, product) = if iterations % 10 == 0 {
+            // every so often shift somewhere made up to shake it out of a cycle
+            let kick = c.abs() + h[last - 1][last - 2].abs();
+            (2.0 * d + kick, (d + kick) * d + kick * kick)
+        } else {
+            // the two eigenvalues of the bottom 2x2 by their sum and product, so it all stays real
+            (a + d, a * d - b * c)
+        };
+        double_shift(&mut h, size, sum, product);
+    }
+    Some(values)
+}
+
+fn split(h: &mut [Vec<f64>], row: usize) -> bool {
+    // whether the number under the diagonal on this row is small enough to call 0
+    let beside = h[row][row].abs() + h[row - 1][row - 1].abs();
+    if h[row][row - 1].abs() <= f64::EPSILON * beside.max(f64::MIN_POSITIVE) {
+        h[row][row - 1] = 0.0;
+        return true;
+    }
+    false
+}
+
+fn corner(h: &[Vec<f64>], top: usize) -> [Complex; 2] {
+    // the eigenvalues of the 2x2 starting at top, half the trace plus or minus the root of the discriminant
+    let (a, b, c, d) = (h[top][top], h[top][top + 1], h[top + 1][top], h[top + 1][top + 1]);
+    let middle = 0.5 * (a + d);
+    let discriminant = 0.25 * (a - d) * (a - d) + b * c;
+    if discriminant >= 0.0 {
+        let root = discriminant.sqrt();
+        [Complex::new(middle + root, 0.0), Complex::new(middle - root, 0.0)]
+    } else {
+        let root = (-discriminant).sqrt();
+        [Complex::new(middle, root), Complex::new(middle, -root)]
+    }
+}
+
+fn reflector(x: &[f64]) -> Option<Vec<f64>> {
+    // the unit v where (I - 2 v v') sends x onto the first axis, None if theres nothing to reflect
+    let length = x.iter().map(|value| value * value).sum::<f64>().sqrt();
+    if length == 0.0 {
+        return None;
+    }
+    let mut v = x.to_vec();
+    v[0] += length.copysign(x[0]);
+    let size = v.iter().map(|value| value * value).sum::<f64>().sqrt();
+    Some(v.iter().map(|value| value / size).collect())
+}
+
+fn reflect_rows(h: &mut [Vec<f64>], v: &[f64], start: usize, columns: usize) {
+    // h = (I - 2 v v') h, the reflection acting on rows start onwards
+    let mut along = vec![0.0; columns];
+    for (x, row) in v.iter().zip(&h[start..]) {
+        for (sum, value) in along.iter_mut().zip(row) {
+            *sum += x * value;
+        }
+    }
+    for (x, row) in v.iter().zip(h[start..].iter_mut()) {
+        for (value, sum) in row.iter_mut().zip(&along) {
+            *value -= 2.0 * x * sum;
+        }
+    }
+}
+
+fn reflect_columns(h: &mut [Vec<f64>], v: &[f64], start: usize, rows: usize) {
+    // h = h (I - 2 v v'), the same thing from the right
+    for row in h.iter_mut().take(rows) {
+        let along: f64 = v.iter().enumerate().map(|(i, x)| x * row[start + i]).sum();
+        for (i, x) in v.iter().enumerate() {
+            row[start + i] -= 2.0 * x * along;
+        }
+    }
+}
+
+fn hessenberg(h: &mut [Vec<f64>], size: usize) {
+    // clears everything under the subdiagonal of the top left size square, one column at a time
+    for column in 0..size.saturating_sub(2) {
+        let below: Vec<f64> = h[(column + 1)..size].iter().map(|row| row[column]).collect();
+        if let Some(v) = reflector(&below) {
+            reflect_rows(h, &v, column + 1, size);
+            reflect_columns(h, &v, column + 1, size);
+        }
+        for row in h.iter_mut().take(size).skip(column + 2) {
+            row[column] = 0.0;
+        }
+    }
+}
+
+fn double_shift(h: &mut [Vec<f64>], size: usize, sum: f64, product: f64) {
+    /*
+    one step on the top left size square. M = H H - sum H + product is real even when the shifts are a
+    complex pair, and Q' H Q with the Q out of M = QR moves the bottom of H towards the shifts. the
+    reflections that make M triangular are the Q, so theyre put straight onto H as they go
+     */
+    let mut m = vec![vec![0.0; size]; size];
+    for (i, row) in m.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            let squared: f64 = (0..size).map(|k| h[i][k] * h[k][j]).sum();
+            *value = squared - sum * h[i][j] + if i == j { product } else { 0.0 };
+        }
+    }
+    for column in 0..(size - 1) {
+        let below: Vec<f64> = m[column..].iter().map(|row| row[column]).collect();
+        if let Some(v) = reflector(&below) {
+            reflect_rows(&mut m, &v, column, size);
+            reflect_rows(h, &v, column, size);
+            reflect_columns(h, &v, column, size);
+        }
+    }
+    // in exact numbers its still hessenberg, this just sweeps up the rounding
+    hessenberg(h, size);
+}
+
+pub fn eigenvector(matrix: &[Vec<f64>], value: Complex) -> Vec<Complex> {
+    /*
+    inverse iteration, solving (A - lambda) x = b over and over pulls b round to the eigenvector.
+    lambda gets nudged a touch so the matrix isnt exactly singular. scaled so the biggest part is 1
+     */
+    let n = matrix.len();
+    let shift = value + Complex::new(1e-10 * (1.0 + value.magnitude()), 0.0);
+    let mut shifted: Vec<Vec<Complex>> = matrix.iter().map(|row| row.iter().map(|x| Complex::new(*x, 0.0)).collect()).collect();
+    for (i, row) in shifted.iter_mut().enumerate() {
+        row[i] = row[i] - shift;
+    }
+    let mut vector = vec![Complex::new(1.0, 0.0); n];
+    for _ in 0..INVERSE_ITERATIONS {
+        vector = solve_complex(&shifted, &vector);
+        let biggest = vector.iter().fold(Complex::new(0.0, 0.0), |biggest, x| if x.magnitude() > biggest.magnitude() { *x } else { biggest });
+        if biggest.magnitude() == 0.0 {
+            break;
+        }
+        vector = vector.iter().map(|x| *x / biggest).collect();
+    }
+    vector
+}
+
+fn solve_complex(matrix: &[Vec<Complex>], right: &[Complex]) -> Vec<Complex> {
+    // gaussian elimination with partial pivoting, tiny pivots are left in as they are thats the point of inverse iteration
+    let n = matrix.len();
+    let mut a: Vec<Vec<Complex>> = matrix.to_vec();
+    let mut b = right.to_vec();
+    for column in 0..n {
+        let pivot = (column..n).max_by(|i, j| a[*i][column].magnitude().total_cmp(&a[*j][column].magnitude())).unwrap_or(column);
+        a.swap(column, pivot);
+        b.swap(column, pivot);
+        if a[column][column].magnitude() == 0.0 {
+            a[column][column] = Complex::new(1e-300, 0.0);
+        }
+        for row in (column + 1)..n {
+            let factor = a[row][column] / a[column][column];
+            let pivot_row = a[column].clone();
+            for (value, above) in a[row].iter_mut().zip(pivot_row).skip(column) {
+                *value = *value - factor * above;
+            }
+            b[row] = b[row] - factor * b[column];
+        }
+    }
+    let mut answer = vec![Complex::new(0.0, 0.0); n];
+    for row in (0..n).rev() {
+        let mut known = Complex::new(0.0, 0.0);
+        for across in (row + 1)..n {
+            known = known + a[row][across] * answer[across];
+        }
+        answer[row] = (b[row] - known) / a[row][row];
+    }
+    answer
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sorted(mut values: Vec<Complex>) -> Vec<Complex> {
+        values.sort_by(|a, b| a.re.total_cmp(&b.re).then(a.im.total_cmp(&b.im)));
+        values
+    }
+
+    #[test]
+    fn finds_real_and_complex_roots() {
+        // a spring mass damper with w = 2 and zeta = 0.25 next to two plain decays
+        let matrix = vec![
+            vec![0.0, 1.0, 0.0, 0.0],
+            vec![-4.0, -1.0, 0.0, 0.0],
+            vec![0.0, 0.0, -3.0, 0.0],
+            vec![0.0, 0.0, 1.0, -0.5],
+        ];
+        let values = sorted(eigenvalues(&matrix).unwrap());
+        let expected = [Complex::new(-3.0, 0.0), Complex::new(-0.5, -(15.0_f64).sqrt() / 2.0), Complex::new(-0.5, (15.0_f64).sqrt() / 2.0), Complex::new(-0.5, 0.0)];
+        let expected = sorted(expected.to_vec());
+        for (value, wanted) in values.iter().zip(expected) {
+            assert!((*value - wanted).magnitude() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn vectors_satisfy_the_equation() {
+        let matrix = vec![
+            vec![1.0, 2.0, 0.5, -1.0, 0.0],
+            vec![-2.0, 0.3, 1.0, 0.0, 0.2],
+            vec![0.1, -0.4, -2.0, 3.0, 1.0],
+            vec![0.0, 0.5, -1.5, -0.2, 0.7],
+            vec![2.0, 0.0, 0.3, -0.6, 1.1],
+        ];
+        let values = eigenvalues(&matrix).unwrap();
+        // they add up to the trace whatever the matrix is
+        let sum = values.iter().fold(Complex::new(0.0, 0.0), |sum, value| sum + *value);
+        assert!((sum - Complex::new(1.0 + 0.3 - 2.0 - 0.2 + 1.1, 0.0)).magnitude() < 1e-9);
+        for value in values {
+            let vector = eigenvector(&matrix, value);
+            for (row, x) in matrix.iter().zip(&vector) {
+                let product = row.iter().zip(&vector).fold(Complex::new(0.0, 0.0), |sum, (a, v)| sum + Complex::new(*a, 0.0) * *v);
+                assert!((product - value * *x).magnitude() < 1e-6);
+            }
+        }
+    }
+}
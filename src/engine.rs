@@ -0,0 +1,232 @@
+use serde::Deserialize;
+
+use crate::atmosphere::{self, AirProperties};
+use crate::common_math::interpolate;
+use crate::definition::AircraftDefinition;
+
+const GAS_CONSTANT: f64 = 287.053;
+
+// how much of best power you get at each fuel air ratio, too lean or too rich and it wont burn
+const MIXTURE_POWER: [(f64, f64); 9] = [
+    (0.0, 0.0), (0.045, 0.0), (0.055, 0.6), (0.067, 0.93), (0.077, 1.0), (0.09, 0.97), (0.11, 0.85), (0.13, 0.5), (0.16, 0.0),
+];
+
+// a normally aspirated piston engine, the numbers for a particular one come from the aircraft file
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct EngineDefinition {
+    pub displacement: f64, // m^3
+    pub volumetric_efficiency: f64,
+    pub specific_work: f64, // J per kg of air at best power
+    pub redline: f64, // rpm
+    pub friction_at_redline: f64, // W
+    pub inertia: f64, // kg m^2, engine and prop together
+    pub firing_rpm: f64, // below this it wont keep running on its own
+    pub full_rich: f64, // fuel air ratio at sea level with the mixture all the way in
+}
+
+impl EngineDefinition {
+    pub fn rated(displacement: f64, volumetric_efficiency: f64, redline: f64, rated_power: f64, inertia: f64, idle_rpm: f64) -> EngineDefinition {
+        /*
+        for when all we know is the book power at redline, like a jsbsim engine file.
+        works the specific work backwards from full throttle at sea level, with the same 12% lost to friction as the lycoming
+         */
+        let full_rich = 0.09;
+        let air = atmosphere::Atmosphere::new().at(0.0);
+        let manifold_density = 0.95 * air.pressure / (GAS_CONSTANT * air.temperature);
+        let air_flow = volumetric_efficiency * displacement * redline / 60.0 / 2.0 * manifold_density;
+        let friction_at_redline = 0.12 * rated_power;
+        EngineDefinition {
+            displacement,
+            volumetric_efficiency,
+            specific_work: (rated_power + friction_at_redline) / (air_flow * interpolate(&MIXTURE_POWER, full_rich)),
+            redline,
+            friction_at_redline,
+            inertia,
+            firing_rpm: idle_rpm / 2.0,
+            full_rich,
+        }
+    }
+}
+
+pub struct Engine {
+    definition: EngineDefinition,
+    pub rpm: f64,
+    pub mixture: f64, // 0 is idle cutoff, 1 is full rich
+    pub manifold_pressure: f64, // Pa
+    pub power: f64, // W at the crankshaft
+    pub fuel_flow: f64, // kg/s
+    pub starved: bool, // nothing coming through from the tanks
+}
+
+impl Engine {
+    pub fn new(definition: EngineDefinition) -> Engine {
+        Engine {
+            definition,
+            rpm: 700.0,
+            mixture: 1.0,
+            manifold_pressure: atmosphere::Atmosphere::new().at(0.0).pressure,
+            power: 0.0,
+            fuel_flow: 0.0,
+            starved: false,
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn lycoming() -> Engine {
+        Engine::new(AircraftDefinition::cessna().engine)
+    }
+
+    pub fn step(&mut self, dt: f64, throttle: f64, air: &AirProperties, propeller_power: f64) {
+        (self.manifold_pressure, self.power, self.fuel_flow) = self.output(throttle, air, self.rpm);
+
+        // whatever power the prop doesnt take speeds the whole thing up
+        let omega = (self.rpm * 2.0 * std::f64::consts::PI / 60.0).max(10.0);
+        let angular_acceleration = (self.power - propeller_power) / (self.definition.inertia * omega);
+        self.rpm = (self.rpm + angular_acceleration * dt * 60.0 / (2.0 * std::f64::consts::PI)).max(0.0);
+    }
+
+    fn output(&self, throttle: f64, air: &AirProperties, rpm: f64) -> (f64, f64, f64) {
+        /*
+        manifold pressure, shaft power and fuel flow at some rpm.
+        the throttle butterfly sets how much of the outside pressure gets into the manifold,
+        the more the engine sucks (higher rpm) the more it drops across the throttle
+         */
+        let engine = &self.definition;
+        let throttle_opening = 0.33 + 0.67 * throttle.clamp(0.0, 1.0) - 0.05 * rpm / engine.redline;
+        let manifold_pressure = air.pressure * throttle_opening.clamp(0.0, 1.0);
+
+        // four stroke so it breathes once every two revs
+        let manifold_density = manifold_pressure / (GAS_CONSTANT * air.temperature);
+        let air_flow = engine.volumetric_efficiency * engine.displacement * rpm / 60.0 / 2.0 * manifold_density;
+
+        // theres no altitude compensation so the mixture goes rich as the air thins out
+        let fuel_air_ratio = self.mixture.clamp(0.0, 1.0) * engine.full_rich * (atmosphere::sea_level_density() / air.density).sqrt();
+        let burning = rpm > engine.firing_rpm && !self.starved;
+        let indicated_power = if burning {
+            air_flow * engine.specific_work * interpolate(&MIXTURE_POWER, fuel_air_ratio)
+        } else {
+            0.0
+        };
+        let fuel_flow = if burning { air_flow * fuel_air_ratio } else { 0.0 };
+        (manifold_pressure, indicated_power - engine.friction_at_redline * (rpm / engine.redline).powf(2.0), fuel_flow)
+    }
+
+    pub fn throttle_for(&self, rpm: f64, air: &AirProperties, propeller_power: f64) -> Result<f64, String> {
+        // the throttle that holds the engine steady at this rpm, if there is one between idle and full
+        let power = |throttle: f64| self.output(throttle, air, rpm).1;
+        if power(1.0) < propeller_power {
+            return Err("theres not enough power for that".to_string());
+        }
+        if power(0.0) > propeller_power {
+            return Err("even at idle theres too much power for that, it needs flaps or a slip".to_string());
+        }
+        let (mut low, mut high) = (0.0, 1.0);
+        for _ in 0..50 {
+            let middle = (low + high) / 2.0;
+            if power(middle) < propeller_power {
+                low = middle;
+            } else {
+                high = middle;
+            }
+        }
+        Ok((low + high) / 2.0)
+    }
+
+    pub fn angular_momentum(&self) -> f64 {
+        // kg m^2/s of the spinning engine and prop, along the crankshaft
+        self.definition.inertia * self.rpm * 2.0 * std::f64::consts::PI / 60.0
+    }
+
+    pub fn manifold_pressure_inhg(&self) -> f64 {
+        self.manifold_pressure / 3386.39
+    }
+
+    pub fn fuel_flow_litres_per_hour(&self) -> f64 {
+        // avgas is about 0.72 kg per litre
+        self.fuel_flow * 3600.0 / 0.72
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::propeller::Propeller;
+
+    fn settle(engine: &mut Engine, throttle: f64, altitude: f64, airspeed: f64) {
+        let air = atmosphere::Atmosphere::new().at(altitude);
+        let propeller = Propeller::cessna();
+        for _ in 0..2000 {
+            let propeller_power = propeller.power_required(airspeed, engine.rpm, air.density);
+            engine.step(0.01, throttle, &air, propeller_power);
+        }
+    }
+
+    #[test]
+    fn full_power_static_run_up() {
+        // the POH static rpm is about 2300 to 2400
+        let mut engine = Engine::lycoming();
+        settle(&mut engine, 1.0, 0.0, 0.0);
+        assert!(engine.rpm > 2200.0 && engine.rpm < 2600.0);
+        assert!(engine.manifold_pressure_inhg() > 27.0 && engine.manifold_pressure_inhg() < 30.0);
+        assert!(engine.fuel_flow_litres_per_hour() > 30.0 && engine.fuel_flow_litres_per_hour() < 70.0);
+    }
+
+    #[test]
+    fn loses_power_up_high() {
+        let mut low = Engine::lycoming();
+        let mut high = Engine::lycoming();
+        settle(&mut low, 1.0, 0.0, 50.0);
+        settle(&mut high, 1.0, 3000.0, 50.0);
+        assert!(high.power < 0.85 * low.power);
+
+        // leaning it out gets some back
+        let mut leaned = Engine::lycoming();
+        leaned.mixture = 0.85;
+        settle(&mut leaned, 1.0, 3000.0, 50.0);
+        assert!(leaned.power > high.power);
+        assert!(leaned.fuel_flow < high.fuel_flow);
+    }
+
+    #[test]
+    fn idle_cutoff_stops_it() {
+        let mut engine = Engine::lycoming();
+        engine.mixture = 0.0;
+        settle(&mut engine, 0.0, 0.0, 0.0);
+        assert!(engine.rpm < engine.definition.firing_rpm);
+        assert_eq!(engine.fuel_flow, 0.0);
+    }
+
+    #[test]
+    fn quits_without_fuel() {
+        let mut engine = Engine::lycoming();
+        settle(&mut engine, 1.0, 0.0, 50.0);
+        engine.starved = true;
+        settle(&mut engine, 1.0, 0.0, 50.0);
+        // its just windmilling now
+        assert!(engine.power < 0.0);
+        assert_eq!(engine.fuel_flow, 0.0);
+    }
+
+    #[test]
+    fn rated_matches_the_lycoming() {
+        // 180hp out of the same engine should come out about the same as the hand picked numbers
+        let lycoming = AircraftDefinition::cessna().engine;
+        let rated = EngineDefinition::rated(lycoming.displacement, lycoming.volumetric_efficiency, lycoming.redline, 180.0 * 745.7, lycoming.inertia, 600.0);
+        assert!((rated.specific_work / lycoming.specific_work - 1.0).abs() < 0.05);
+        assert!((rated.friction_at_redline / lycoming.friction_at_redline - 1.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn finds_the_throttle_for_an_rpm() {
+        // going the other way, the rpm it settles at should give back the throttle that got it there
+        let mut engine = Engine::lycoming();
+        settle(&mut engine, 0.6, 1000.0, 50.0);
+        let air = atmosphere::Atmosphere::new().at(1000.0);
+        let propeller_power = Propeller::cessna().power_required(50.0, engine.rpm, air.density);
+        let throttle = engine.throttle_for(engine.rpm, &air, propeller_power).unwrap();
+        assert!((throttle - 0.6).abs() < 0.01);
+        // and theres nothing that turns it at redline going this slow
+        assert!(engine.throttle_for(2700.0, &air, Propeller::cessna().power_required(20.0, 2700.0, air.density)).is_err());
+    }
+}
@@ -0,0 +1,82 @@
+use serde::Deserialize;
+
+use crate::common_math::{rad_to_deg, Vec3};
+use crate::definition::AircraftDefinition;
+
+// at spin angles of attack the wake off an up elevator covers the bottom of the rudder
+const BLANKETED: f64 = 0.6;
+const BLANKETING_ALPHA: (f64, f64) = (15.0, 30.0); // degrees, where it starts and where its as bad as it gets
+
+// the vertical stabiliser, done as its own little wing sat behind and above the CG
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Fin {
+    pub area: f64,
+    pub position: Vec3, // relative to the reference point in body coords
+    pub lift_slope: f64, // per radian, low because the fin is short and stubby
+    pub rudder_effectiveness: f64, // how much of a radian of sideslip one radian of rudder is worth
+}
+
+impl Fin {
+    #[allow(dead_code)]
+    pub fn cessna() -> Fin {
+        AircraftDefinition::cessna().fin
+    }
+
+    pub fn forces(&self, body_velocity: &Vec3, angular_rate: &Vec3, rudder: f64, density: f64) -> (Vec3, Vec3) {
+        /*
+        returns the side force and the moment it makes about the reference point, both in body coords
+        the air at the fin also has the rotation of the plane in it, which is where the yaw damping comes from
+         */
+        let local_velocity = *body_velocity + &angular_rate.cross_product(&self.position);
+        let local_airspeed = local_velocity.magnitude();
+        if local_airspeed < 1.0 {
+            return (Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0));
+        }
+        // positive sideslip is air coming from the right, which pushes the fin left (+y)
+        let sideslip = (-local_velocity.y / local_airspeed).asin();
+        let dynamic_pressure = 0.5 * density * local_airspeed.powf(2.0);
+        // positive rudder swings the nose left, so it pushes the tail right
+        let side_force = dynamic_pressure * self.area * self.lift_slope * (sideslip - self.rudder_effectiveness * rudder);
+        let force = Vec3::new(0.0, side_force, 0.0);
+        (force, self.position.cross_product(&force))
+    }
+
+    pub fn working(&self, alpha: f64, elevator_up: f64) -> f64 {
+        // how much of the fin is still out in clean air, which is why the elevator has to go forward to stop a spin
+        let (start, worst) = BLANKETING_ALPHA;
+        let deep = ((rad_to_deg(alpha.abs()) - start) / (worst - start)).clamp(0.0, 1.0);
+        1.0 - BLANKETED * deep * elevator_up.clamp(0.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn weathercocks() {
+        // air from the right should push the tail left, so the nose swings right into the wind
+        let fin = Fin::cessna();
+        let (force, moment) = fin.forces(&Vec3::new(40.0, -4.0, 0.0), &Vec3::new(0.0, 0.0, 0.0), 0.0, 1.225);
+        assert!(force.y > 0.0);
+        assert!(moment.z < 0.0);
+        // and it sits above the CG so it rolls the plane away from the wind as well
+        assert!(moment.x < 0.0);
+    }
+
+    #[test]
+    fn damps_yaw() {
+        let fin = Fin::cessna();
+        let (_, moment) = fin.forces(&Vec3::new(40.0, 0.0, 0.0), &Vec3::new(0.0, 0.0, 0.5), 0.0, 1.225);
+        assert!(moment.z < 0.0);
+    }
+
+    #[test]
+    fn blanketed_by_the_elevator() {
+        let fin = Fin::cessna();
+        assert_eq!(fin.working(0.1, 1.0), 1.0);
+        assert!(fin.working(0.6, 1.0) < 0.5);
+        assert_eq!(fin.working(0.6, 0.0), 1.0);
+    }
+}
@@ -0,0 +1,129 @@
+use crate::common_math::interpolate;
+
+// the 172 has notches at 0, 10, 20 and 30 degrees and an electric motor that does about 3 degrees a second
+const SETTINGS: [f64; 4] = [0.0, 10.0, 20.0, 30.0];
+const RATE: f64 = 3.0; // deg/s
+
+// increments against flap position in degrees, roughly from the jsbsim 172
+const LIFT: [(f64, f64); 4] = [(0.0, 0.0), (10.0, 0.2), (20.0, 0.3), (30.0, 0.35)];
+const MAX_LIFT: [(f64, f64); 4] = [(0.0, 0.0), (10.0, 0.25), (20.0, 0.4), (30.0, 0.5)];
+const DRAG: [(f64, f64); 4] = [(0.0, 0.0), (10.0, 0.01), (20.0, 0.03), (30.0, 0.06)];
+const MOMENT: [(f64, f64); 4] = [(0.0, 0.0), (10.0, -0.0654), (20.0, -0.0981), (30.0, -0.114)];
+
+// flap extended speeds, 110 knots for the first notch and 85 past that
+const VFE_FIRST_NOTCH: f64 = 56.6; // m/s
+const VFE_FULL: f64 = 43.7; // m/s
+const DAMAGE_MARGIN: f64 = 1.2; // this much over vfe and something bends
+
+pub struct FlapIncrements {
+    pub lift: f64,
+    pub max_lift: f64,
+    pub drag: f64,
+    pub moment: f64,
+}
+
+impl FlapIncrements {
+    pub fn at(position: f64) -> FlapIncrements {
+        // position in degrees
+        FlapIncrements {
+            lift: interpolate(&LIFT, position),
+            max_lift: interpolate(&MAX_LIFT, position),
+            drag: interpolate(&DRAG, position),
+            moment: interpolate(&MOMENT, position),
+        }
+    }
+}
+
+pub struct Flaps {
+    pub lever: usize, // which notch is selected
+    pub position: f64, // where they actually are, in degrees
+    pub damaged: bool, // jammed where they were when they got overstressed
+}
+
+impl Flaps {
+    pub fn new() -> Flaps {
+        Flaps {
+            lever: 0,
+            position: 0.0,
+            damaged: false,
+        }
+    }
+
+    pub fn lever_down(&mut self) {
+        self.lever = (self.lever + 1).min(SETTINGS.len() - 1);
+    }
+
+    pub fn lever_up(&mut self) {
+        self.lever = self.lever.saturating_sub(1);
+    }
+
+    pub fn step(&mut self, dt: f64, airspeed: f64) {
+        // the limits are indicated airspeeds, so this wants the equivalent airspeed not the true one
+        if airspeed > DAMAGE_MARGIN * self.extended_speed() {
+            self.damaged = true;
+        }
+        if self.damaged {
+            return;
+        }
+        let target = SETTINGS[self.lever];
+        let travel = (target - self.position).clamp(-RATE * dt, RATE * dt);
+        self.position += travel;
+    }
+
+    pub fn extended_speed(&self) -> f64 {
+        // the limit for wherever the flaps are right now, clean is unlimited
+        if self.position <= 0.0 {
+            f64::INFINITY
+        } else if self.position <= SETTINGS[1] {
+            VFE_FIRST_NOTCH
+        } else {
+            VFE_FULL
+        }
+    }
+
+    pub fn overspeed(&self, airspeed: f64) -> bool {
+        airspeed > self.extended_speed()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn takes_time_to_run_down() {
+        let mut flaps = Flaps::new();
+        flaps.lever_down();
+        flaps.lever_down();
+        flaps.lever_down();
+        flaps.lever_down();
+        assert_eq!(flaps.lever, 3);
+        flaps.step(1.0, 30.0);
+        assert_eq!(flaps.position, RATE);
+        for _ in 0..20 {
+            flaps.step(1.0, 30.0);
+        }
+        assert_eq!(flaps.position, 30.0);
+        assert!(FlapIncrements::at(flaps.position).lift > 0.3);
+        assert!(FlapIncrements::at(flaps.position).moment < 0.0);
+    }
+
+    #[test]
+    fn overspeed_warns_then_breaks() {
+        let mut flaps = Flaps::new();
+        assert!(!flaps.overspeed(80.0));
+        flaps.lever_down();
+        for _ in 0..10 {
+            flaps.step(1.0, 30.0);
+        }
+        assert!(flaps.overspeed(60.0));
+        assert!(!flaps.damaged);
+
+        flaps.step(1.0, 75.0);
+        assert!(flaps.damaged);
+        // and now theyre stuck
+        flaps.lever_up();
+        flaps.step(1.0, 30.0);
+        assert_eq!(flaps.position, 10.0);
+    }
+}
@@ -0,0 +1,108 @@
+use crate::balance::{Balance, Tank};
+
+// whats sat in the lines and the gascolator, the engine keeps going on this for a few seconds after the tank runs dry
+const LINE_FUEL: f64 = 0.03; // kg
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Selector {
+    Both,
+    Left,
+    Right,
+    Off,
+}
+
+impl Selector {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Selector::Both => "BOTH",
+            Selector::Left => "LEFT",
+            Selector::Right => "RIGHT",
+            Selector::Off => "OFF",
+        }
+    }
+
+    pub fn next(&self) -> Selector {
+        // round the valve the way it turns in the plane
+        match self {
+            Selector::Both => Selector::Right,
+            Selector::Right => Selector::Off,
+            Selector::Off => Selector::Left,
+            Selector::Left => Selector::Both,
+        }
+    }
+
+    pub fn feeds(&self, tank: &Tank) -> bool {
+        // body y points left, so the left wing tank is the one out on +y
+        match self {
+            Selector::Both => true,
+            Selector::Left => tank.position.y >= 0.0,
+            Selector::Right => tank.position.y <= 0.0,
+            Selector::Off => false,
+        }
+    }
+}
+
+pub struct FuelSystem {
+    pub selector: Selector,
+    line: f64, // kg between the selector and the engine
+}
+
+impl FuelSystem {
+    pub fn new() -> FuelSystem {
+        FuelSystem {
+            selector: Selector::Both,
+            line: LINE_FUEL,
+        }
+    }
+
+    pub fn step(&mut self, balance: &mut Balance, fuel_flow: f64, dt: f64) -> bool {
+        /*
+        the engine drinks out of the lines and the lines fill back up from whatever tanks are selected,
+        returns whether theres any fuel getting to the engine. a plane with no tanks never runs out
+         */
+        if balance.tanks.is_empty() {
+            return true;
+        }
+        self.line = (self.line - fuel_flow * dt).max(0.0);
+        let selector = self.selector;
+        self.line += balance.draw(LINE_FUEL - self.line, |tank| selector.feeds(tank));
+        self.line > 0.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::definition::AircraftDefinition;
+
+    #[test]
+    fn selector_picks_the_tanks() {
+        let mut balance = AircraftDefinition::cessna().balance;
+        let mut fuel = FuelSystem::new();
+        fuel.selector = Selector::Left;
+        for _ in 0..100 {
+            assert!(fuel.step(&mut balance, 0.01, 1.0));
+        }
+        // all out of the left, and the right hasnt been touched
+        assert!((balance.tanks[0].fuel - (balance.tanks[0].capacity - 1.0)).abs() < 1e-9);
+        assert_eq!(balance.tanks[1].fuel, balance.tanks[1].capacity);
+        assert_eq!(Selector::Off.next().next(), Selector::Both);
+    }
+
+    #[test]
+    fn starves_once_the_lines_are_empty() {
+        let mut balance = AircraftDefinition::cessna().balance;
+        let mut fuel = FuelSystem::new();
+        fuel.selector = Selector::Off;
+        // it runs on whats in the lines for a bit, then stops
+        assert!(fuel.step(&mut balance, 0.01, 1.0));
+        let mut seconds = 1;
+        while fuel.step(&mut balance, 0.01, 1.0) {
+            seconds += 1;
+        }
+        assert!(seconds > 1 && seconds < 10);
+        // switching to a tank with fuel in it brings it back
+        fuel.selector = Selector::Right;
+        assert!(fuel.step(&mut balance, 0.01, 1.0));
+    }
+}
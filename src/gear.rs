@@ -0,0 +1,160 @@
+use serde::Deserialize;
+
+use crate::common_math::Vec3;
+use crate::controls;
+use crate::definition::AircraftDefinition;
+use crate::state::State;
+
+const ROLLING_FRICTION: f64 = 0.02;
+const BRAKE_FRICTION: f64 = 0.5; // with the toe brakes stood on
+const SIDE_FRICTION: f64 = 0.8; // tyres on dry tarmac before they start to skid
+const SLIP_SPEED: f64 = 0.1; // m/s, friction ramps up over this so the plane doesnt jitter when its stopped
+
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct GearLeg {
+    pub position: Vec3, // where the tyre touches, in body coords from the reference point
+    pub spring: f64, // N/m
+    pub damping: f64, // N/(m/s)
+    #[serde(default)]
+    pub max_steer: f64, // degrees the wheel turns at full rudder, 0 if it doesnt steer
+    pub brake: Brake,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Brake {
+    None,
+    Left,
+    Right,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Gear {
+    pub legs: Vec<GearLeg>,
+    #[serde(skip)]
+    pub left_brake: f64, // 0 to 1, how hard the toe brake is pushed
+    #[serde(skip)]
+    pub right_brake: f64,
+}
+
+impl Gear {
+    #[allow(dead_code)]
+    pub fn cessna() -> Gear {
+        // the springs are picked so the plane sits level with about 8cm of squash on each leg
+        AircraftDefinition::cessna().gear
+    }
+
+    pub fn rest_height(&self, weight: f64) -> f64 {
+        // how high the reference point sits when the springs are holding the plane up level, assuming they all touch
+        let stiffness: f64 = self.legs.iter().map(|leg| leg.spring).sum();
+        let lowest = self.legs.iter().map(|leg| -leg.position.z).fold(0.0, f64::max);
+        let height = (self.legs.iter().map(|leg| -leg.position.z * leg.spring).sum::<f64>() - weight) / stiffness;
+        height.min(lowest)
+    }
+
+    pub fn on_ground(&self, state: &State) -> bool {
+        self.legs.iter().any(|leg| (state.position + &state.attitude.rotate(&leg.position)).z < 0.0)
+    }
+
+    pub fn forces(&self, state: &State, rudder: f64) -> (Vec3, Vec3) {
+        // returns the force and moment about the reference point in body coords, same as the aero
+        let mut force = Vec3::new(0.0, 0.0, 0.0);
+        let mut moment = Vec3::new(0.0, 0.0, 0.0);
+        for leg in &self.legs {
+            let contact = state.position + &state.attitude.rotate(&leg.position);
+            if contact.z >= 0.0 {
+                continue;
+            }
+            let compression = -contact.z;
+            let leg_velocity = state.velocity + &state.attitude.rotate(&state.angular_rate.cross_product(&leg.position));
+
+            // the ground can push but it cant pull
+            let normal = (leg.spring * compression - leg.damping * leg_velocity.z).max(0.0);
+
+            // which way the tyre is pointing, flattened onto the ground
+            // its linked to the rudder pedals, so full rudder is full steering
+            let steering = rudder * leg.max_steer / controls::RUDDER_TRAVEL;
+            let heading = state.attitude.rotate(&Vec3::new(steering.cos(), steering.sin(), 0.0));
+            let forwards = match Vec3::new(heading.x, heading.y, 0.0).unit_vector() {
+                Some(forwards) => forwards,
+                None => continue, // pointing straight up or down, its not rolling on anything
+            };
+            let sideways = Vec3::new(-forwards.y, forwards.x, 0.0);
+
+            let braking = match leg.brake {
+                Brake::Left => self.left_brake,
+                Brake::Right => self.right_brake,
+                Brake::None => 0.0,
+            };
+            let rolling = (ROLLING_FRICTION + BRAKE_FRICTION * braking.clamp(0.0, 1.0)) * normal;
+            let along = -rolling * (leg_velocity.dot_product(&forwards) / SLIP_SPEED).clamp(-1.0, 1.0);
+            let across = -SIDE_FRICTION * normal * (leg_velocity.dot_product(&sideways) / SLIP_SPEED).clamp(-1.0, 1.0);
+
+            let leg_force = state.attitude.rotate_inverse(&(Vec3::new(0.0, 0.0, normal) + &(forwards * along) + &(sideways * across)));
+            force = force + &leg_force;
+            moment = moment + &leg.position.cross_product(&leg_force);
+        }
+        (force, moment)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::common_math::deg_to_rad;
+
+    #[test]
+    fn holds_the_plane_up() {
+        let gear = Gear::cessna();
+        let (force, moment) = gear.forces(&State::runway(), 0.0);
+        // close enough to a 1156kg plane, and not trying to tip it over
+        assert!((force.z - 1156.0 * 9.81).abs() < 0.05 * 1156.0 * 9.81);
+        assert!(moment.y.abs() < 500.0);
+        assert!(gear.on_ground(&State::runway()));
+        assert!((gear.rest_height(1156.0 * 9.81) - State::runway().position.z).abs() < 0.01);
+
+        let mut airborne = State::runway();
+        airborne.position.z = 2.0;
+        assert!(!gear.on_ground(&airborne));
+        assert_eq!(gear.forces(&airborne, 0.0).0.magnitude(), 0.0);
+    }
+
+    #[test]
+    fn brakes_and_friction() {
+        let mut gear = Gear::cessna();
+        let mut rolling = State::runway();
+        rolling.velocity.x = 10.0;
+        let coasting = gear.forces(&rolling, 0.0).0.x;
+        assert!(coasting < 0.0);
+        gear.left_brake = 1.0;
+        gear.right_brake = 1.0;
+        let braking = gear.forces(&rolling, 0.0).0.x;
+        assert!(braking < 10.0 * coasting);
+
+        // only the left brake pulls the nose left
+        gear.right_brake = 0.0;
+        assert!(gear.forces(&rolling, 0.0).1.z > 0.0);
+    }
+
+    #[test]
+    fn steers_and_resists_skidding() {
+        let gear = Gear::cessna();
+        let mut rolling = State::runway();
+        rolling.velocity.x = 10.0;
+        // left rudder turns the nosewheel left, which pushes the nose left
+        let (force, moment) = gear.forces(&rolling, deg_to_rad(16.0));
+        assert!(force.y > 0.0);
+        assert!(moment.z > 0.0);
+        // a wheel that doesnt steer doesnt turn it
+        let mut fixed = Gear::cessna();
+        fixed.legs[0].max_steer = 0.0;
+        assert!(fixed.forces(&rolling, deg_to_rad(16.0)).1.z.abs() < 1e-6);
+
+        // sliding sideways, the tyres push back
+        let mut skidding = State::runway();
+        skidding.velocity.y = 2.0;
+        assert!(gear.forces(&skidding, 0.0).0.y < 0.0);
+    }
+}
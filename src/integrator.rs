@@ -0,0 +1,248 @@
+use crate::common_math::{Quaternion, Vec3};
+use crate::state::State;
+
+// dormand prince 5(4), the same one matlabs ode45 uses
+const DP_C: [f64; 7] = [0.0, 1.0 / 5.0, 3.0 / 10.0, 4.0 / 5.0, 8.0 / 9.0, 1.0, 1.0];
+const DP_A: [[f64; 6]; 7] = [
+    [0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+    [1.0 / 5.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+    [3.0 / 40.0, 9.0 / 40.0, 0.0, 0.0, 0.0, 0.0],
+    [44.0 / 45.0, -56.0 / 15.0, 32.0 / 9.0, 0.0, 0.0, 0.0],
+    [19372.0 / 6561.0, -25360.0 / 2187.0, 64448.0 / 6561.0, -212.0 / 729.0, 0.0, 0.0],
+    [9017.0 / 3168.0, -355.0 / 33.0, 46732.0 / 5247.0, 49.0 / 176.0, -5103.0 / 18656.0, 0.0],
+    [35.0 / 384.0, 0.0, 500.0 / 1113.0, 125.0 / 192.0, -2187.0 / 6784.0, 11.0 / 84.0],
+];
+const DP_FIFTH: [f64; 7] = [35.0 / 384.0, 0.0, 500.0 / 1113.0, 125.0 / 192.0, -2187.0 / 6784.0, 11.0 / 84.0, 0.0];
+const DP_FOURTH: [f64; 7] = [
+    5179.0 / 57600.0, 0.0, 7571.0 / 16695.0, 393.0 / 640.0, -92097.0 / 339200.0, 187.0 / 2100.0, 1.0 / 40.0,
+];
+const MAX_SUBSTEPS: usize = 1000; // so a nasty bit of physics cant hang the frame
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Integrator {
+    Euler,
+    SemiImplicitEuler,
+    RungeKutta4,
+    DormandPrince { tolerance: f64 }, // adaptive, takes as many substeps as it needs to fill dt
+}
+
+#[derive(Clone, Copy)]
+pub struct StateDerivative {
+    pub velocity: Vec3, // world, m/s
+    pub acceleration: Vec3, // world, m/s^2
+    pub attitude: Quaternion, // dq/dt
+    pub angular_acceleration: Vec3, // body, rad/s^2
+}
+
+impl StateDerivative {
+    fn zero() -> StateDerivative {
+        StateDerivative {
+            velocity: Vec3::new(0.0, 0.0, 0.0),
+            acceleration: Vec3::new(0.0, 0.0, 0.0),
+            attitude: Quaternion::new(0.0, 0.0, 0.0, 0.0),
+            angular_acceleration: Vec3::new(0.0, 0.0, 0.0),
+        }
+    }
+
+    fn weighted(terms: &[(f64, &StateDerivative)]) -> StateDerivative {
+        // sum of weight * derivative, which is what every runge kutta step boils down to
+        let mut total = StateDerivative::zero();
+        for (weight, term) in terms {
+            if *weight == 0.0 {
+                continue;
+            }
+            total.velocity = total.velocity + &(term.velocity * *weight);
+            total.acceleration = total.acceleration + &(term.acceleration * *weight);
+            total.attitude = total.attitude + &(term.attitude * *weight);
+            total.angular_acceleration = total.angular_acceleration + &(term.angular_acceleration * *weight);
+        }
+        total
+    }
+}
+
+fn advance(state: &State, derivative: &StateDerivative, dt: f64) -> State {
+    State {
+        position: state.position + &(derivative.velocity * dt),
+        velocity: state.velocity + &(derivative.acceleration * dt),
+        attitude: (state.attitude + &(derivative.attitude * dt)).normalised(),
+        angular_rate: state.angular_rate + &(derivative.angular_acceleration * dt),
+        acceleration: derivative.acceleration,
+        wind: state.wind,
+        gust_rate: state.gust_rate,
+    }
+}
+
+fn relative_error(error: &Vec3, size: &Vec3, tolerance: f64) -> f64 {
+    error.magnitude() / (tolerance * (1.0 + size.magnitude()))
+}
+
+impl Integrator {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Integrator::Euler => "euler",
+            Integrator::SemiImplicitEuler => "semi implicit euler",
+            Integrator::RungeKutta4 => "rk4",
+            Integrator::DormandPrince { .. } => "rk45",
+        }
+    }
+
+    pub fn step<F: Fn(&State) -> StateDerivative>(&self, state: &State, dt: f64, derivative: F) -> State {
+        // moves the whole rigid body state on by dt, derivative is whatever the forces and moments say it should be doing
+        match self {
+            Integrator::Euler => advance(state, &derivative(state), dt),
+            Integrator::SemiImplicitEuler => {
+                // kick the rates first and then move with the new ones, keeps oscillations from growing
+                let k = derivative(state);
+                let velocity = state.velocity + &(k.acceleration * dt);
+                let angular_rate = state.angular_rate + &(k.angular_acceleration * dt);
+                State {
+                    position: state.position + &(velocity * dt),
+                    velocity,
+                    attitude: state.attitude.integrate(&angular_rate, dt),
+                    angular_rate,
+                    acceleration: k.acceleration,
+                    wind: state.wind,
+                    gust_rate: state.gust_rate,
+                }
+            },
+            Integrator::RungeKutta4 => {
+                let k1 = derivative(state);
+                let k2 = derivative(&advance(state, &k1, dt / 2.0));
+                let k3 = derivative(&advance(state, &k2, dt / 2.0));
+                let k4 = derivative(&advance(state, &k3, dt));
+                let average = StateDerivative::weighted(&[(1.0 / 6.0, &k1), (1.0 / 3.0, &k2), (1.0 / 3.0, &k3), (1.0 / 6.0, &k4)]);
+                advance(state, &average, dt)
+            },
+            Integrator::DormandPrince { tolerance } => {
+                let mut current = *state;
+                let mut time = 0.0;
+                let mut h = dt;
+                let mut substeps = 0;
+                while time < dt {
+                    h = h.min(dt - time);
+                    let (next, error) = dormand_prince_step(&current, h, &derivative);
+                    let error = relative_error(&error.position, &current.position, *tolerance)
+                        .max(relative_error(&error.velocity, &current.velocity, *tolerance))
+                        .max(relative_error(&error.angular_rate, &current.angular_rate, *tolerance))
+                        .max(error.attitude.magnitude() / *tolerance);
+                    substeps += 1;
+                    if error <= 1.0 || substeps >= MAX_SUBSTEPS {
+                        current = next;
+                        time += h;
+                    }
+                    // the usual step size controller, grow slowly and shrink fast
+                    let scale = if error == 0.0 { 5.0 } else { (0.9 * error.powf(-0.2)).clamp(0.2, 5.0) };
+                    h *= scale;
+                }
+                current
+            },
+        }
+    }
+}
+
+fn dormand_prince_step<F: Fn(&State) -> StateDerivative>(state: &State, h: f64, derivative: &F) -> (State, State) {
+    // returns the fifth order answer and the difference to the fourth order one
+    let mut stages: Vec<StateDerivative> = Vec::with_capacity(DP_C.len());
+    for row in DP_A.iter() {
+        let terms: Vec<(f64, &StateDerivative)> = row.iter().cloned().zip(stages.iter()).collect();
+        let slope = StateDerivative::weighted(&terms);
+        stages.push(derivative(&advance(state, &slope, h)));
+    }
+    let fifth: Vec<(f64, &StateDerivative)> = DP_FIFTH.iter().cloned().zip(stages.iter()).collect();
+    let difference: Vec<(f64, &StateDerivative)> = DP_FIFTH.iter().zip(DP_FOURTH.iter()).map(|(a, b)| a - b).zip(stages.iter()).collect();
+    let next = advance(state, &StateDerivative::weighted(&fifth), h);
+    let difference = StateDerivative::weighted(&difference);
+    let error = State {
+        position: difference.velocity * h,
+        velocity: difference.acceleration * h,
+        // just the difference itself, its already in the same units as a unit quaternion
+        attitude: difference.attitude * h,
+        angular_rate: difference.angular_acceleration * h,
+        acceleration: difference.acceleration,
+        wind: state.wind,
+        gust_rate: state.gust_rate,
+    };
+    (next, error)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn spring(state: &State) -> StateDerivative {
+        // a unit mass on a unit spring along x, spinning steadily about z
+        StateDerivative {
+            velocity: state.velocity,
+            acceleration: state.position * -1.0,
+            attitude: state.attitude.derivative(&state.angular_rate),
+            angular_acceleration: Vec3::new(0.0, 0.0, 0.0),
+        }
+    }
+
+    fn error_after(integrator: Integrator, time: f64, dt: f64) -> f64 {
+        let mut state = State::new();
+        state.position.x = 1.0;
+        state.angular_rate.z = 1.0;
+        let steps = (time / dt).round() as usize;
+        for _ in 0..steps {
+            state = integrator.step(&state, dt, spring);
+        }
+        // x = cos t, and the heading should have gone round by t radians
+        let heading = state.attitude.as_angles().azimouth.to_radians();
+        (state.position.x - time.cos()).abs() + (heading - time).abs()
+    }
+
+    #[test]
+    fn converges_as_dt_shrinks() {
+        for integrator in [Integrator::Euler, Integrator::SemiImplicitEuler, Integrator::RungeKutta4] {
+            let coarse = error_after(integrator, 2.0, 0.02);
+            let fine = error_after(integrator, 2.0, 0.01);
+            assert!(fine < coarse);
+        }
+        // fourth order, so halving dt should take off about a factor of 16
+        let coarse = error_after(Integrator::RungeKutta4, 2.0, 0.1);
+        let fine = error_after(Integrator::RungeKutta4, 2.0, 0.05);
+        assert!(coarse / fine > 10.0);
+        assert!(fine < error_after(Integrator::Euler, 2.0, 0.05));
+    }
+
+    #[test]
+    fn semi_implicit_keeps_energy() {
+        // plain euler spirals out, semi implicit just wobbles about the right answer
+        let energy = |integrator: Integrator| {
+            let mut state = State::new();
+            state.position.x = 1.0;
+            for _ in 0..5000 {
+                state = integrator.step(&state, 0.05, spring);
+            }
+            state.position.magnitude().powf(2.0) + state.velocity.magnitude().powf(2.0)
+        };
+        assert!(energy(Integrator::Euler) > 2.0);
+        assert!((energy(Integrator::SemiImplicitEuler) - 1.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn adaptive_meets_tolerance() {
+        // one big step, it has to cut it up itself
+        let error = error_after(Integrator::DormandPrince { tolerance: 1e-8 }, 2.0, 2.0);
+        assert!(error < 1e-5);
+    }
+
+    #[test]
+    fn adaptive_watches_the_attitude() {
+        // sat still and spinning fast, so the attitude is the only thing with any error in it
+        let spin = |state: &State| StateDerivative {
+            velocity: Vec3::new(0.0, 0.0, 0.0),
+            acceleration: Vec3::new(0.0, 0.0, 0.0),
+            attitude: state.attitude.derivative(&state.angular_rate),
+            angular_acceleration: Vec3::new(0.0, 0.0, 0.0),
+        };
+        let mut state = State::new();
+        state.angular_rate = Vec3::new(1.5, 0.0, 5.0);
+        let (next, error) = dormand_prince_step(&state, 0.5, &spin);
+        let exact = Quaternion::from_axis_angle(&Vec3::new(1.5, 0.0, 5.0).unit_vector().unwrap(), 0.5 * 27.25_f64.sqrt());
+        let actual = (next.attitude + &(exact * -1.0)).magnitude();
+        // the estimate should be about as big as the error really is, not squared away to nothing
+        assert!(error.attitude.magnitude() > actual / 3.0 && error.attitude.magnitude() < actual * 3.0);
+    }
+}
@@ -0,0 +1,853 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use roxmltree::{Document, Node};
+
+use crate::aero::{body_force, AeroInputs, AeroModel};
+use crate::balance::{Balance, Station, Tank};
+use crate::common_math::{deg_to_rad, rad_to_deg, Vec3};
+use crate::definition::{AircraftDefinition, Geometry, MassProperties};
+use crate::engine::EngineDefinition;
+use crate::gear::{Brake, Gear, GearLeg};
+use crate::propeller::Propeller;
+use crate::touchdown::{Contact, CrashCause};
+use crate::wing::WingDefinition;
+
+// jsbsim works in feet and pounds, the aero functions give lbf and ft lbf
+const POUNDS_FORCE: f64 = 4.4482216; // N
+const FOOT_POUNDS: f64 = 1.3558179; // N m
+const PASCALS_PER_PSF: f64 = 47.880259;
+const HORSEPOWER: f64 = 745.7; // W
+
+// the axes the aero functions get summed into, in the order jsbsim lists them
+const AXES: [&str; 6] = ["DRAG", "SIDE", "LIFT", "ROLL", "PITCH", "YAW"];
+
+/*
+reads a jsbsim aircraft (the fdm_config xml) into one of our definitions. it does the metrics, mass_balance,
+ground_reactions, one piston engine and its prop, and the aerodynamics functions, which is what the stock c172x uses.
+anything we dont model (the fcs, autopilot, systems) gets ignored, and properties the aero asks for that we dont
+have come out as 0 with a warning
+ */
+pub fn load(path: &str) -> Result<AircraftDefinition, String> {
+    let text = std::fs::read_to_string(path).map_err(|error| format!("couldnt read {}: {}", path, error))?;
+    let directory = Path::new(path).parent().unwrap_or(Path::new("."));
+    parse(&text, directory).map_err(|error| format!("{}: {}", path, error))
+}
+
+pub fn parse(text: &str, directory: &Path) -> Result<AircraftDefinition, String> {
+    let document = Document::parse(text).map_err(|error| error.to_string())?;
+    let root = document.root_element();
+    if root.tag_name().name() != "fdm_config" {
+        return Err("thats not a jsbsim aircraft, theres no <fdm_config>".to_string());
+    }
+    // the bits jsbsim doesnt describe the way we do come from the 172
+    let defaults = AircraftDefinition::cessna();
+
+    let metrics = section(&root, "metrics")?;
+    let area = measured(&metrics, "wingarea", "FT2")?;
+    let span = measured(&metrics, "wingspan", "FT")?;
+    let chord = measured(&metrics, "chord", "FT")?;
+
+    let propulsion = root.children().find(|node| node.has_tag_name("propulsion"));
+    let (mass, balance, cg) = mass_balance(&section(&root, "mass_balance")?, propulsion.as_ref())?;
+    let aerorp = match named_location(&metrics, "AERORP")? {
+        Some(location) => to_body(&location, &cg),
+        None => Vec3::new(0.0, 0.0, 0.0),
+    };
+
+    let propulsion = propulsion.ok_or("theres no <propulsion>, it needs an engine")?;
+    let (engine, propeller, thrust_position) = engine(&propulsion, directory, &cg)?;
+
+    let ground_reactions = section(&root, "ground_reactions")?;
+    let legs = gear_legs(&ground_reactions, &cg)?;
+    let mut contacts = structure_contacts(&ground_reactions, &cg)?;
+
+    let mut aerodynamics = Aerodynamics::parse(&section(&root, "aerodynamics")?, area, span, chord, aerorp)?;
+    // say what got borrowed, so nobody thinks its all out of the file
+    aerodynamics.warnings.push("theres no [aero] in a jsbsim file, the hand tuned coefficients are the 172s".to_string());
+    aerodynamics.warnings.push("theres no fin in a jsbsim file, its the 172s".to_string());
+    aerodynamics.warnings.push("the wing lift curves, drag and dihedral are the 172s, only the stall angles come from the file".to_string());
+    if contacts.is_empty() {
+        aerodynamics.warnings.push("theres no STRUCTURE contacts, it crashes on the 172s wing tips, tail, prop and belly".to_string());
+        contacts = defaults.geometry.contacts.clone();
+    }
+    let wing = WingDefinition {
+        critical_alpha: aerodynamics.stall_alpha.1,
+        reattach_alpha: aerodynamics.stall_alpha.0,
+        ..defaults.wing
+    };
+
+    let definition = AircraftDefinition {
+        name: root.attribute("name").unwrap_or("jsbsim aircraft").to_string(),
+        geometry: Geometry { area, chord, span, thrust_position, wing_position: aerorp, contacts },
+        mass,
+        balance,
+        aero: defaults.aero,
+        derivatives: None,
+        wing,
+        fin: defaults.fin,
+        propeller,
+        engine,
+        gear: Gear { legs, left_brake: 0.0, right_brake: 0.0 },
+        aero_tables: Some(aerodynamics),
+    };
+    definition.check()?;
+    Ok(definition)
+}
+
+fn section<'a, 'input>(parent: &Node<'a, 'input>, name: &str) -> Result<Node<'a, 'input>, String> {
+    parent.children().find(|node| node.has_tag_name(name)).ok_or(format!("theres no <{}>", name))
+}
+
+fn number(node: &Node) -> Result<f64, String> {
+    let text = node.text().unwrap_or("").trim();
+    text.parse().map_err(|_| format!("<{}> should be a number, not {:?}", node.tag_name().name(), text))
+}
+
+fn numbers(node: &Node) -> Result<Vec<f64>, String> {
+    node.text().unwrap_or("").split_whitespace()
+        .map(|word| word.parse().map_err(|_| format!("<{}> should be numbers, not {:?}", node.tag_name().name(), word)))
+        .collect()
+}
+
+fn to_si(value: f64, unit: &str) -> Result<f64, String> {
+    let scale = match unit {
+        "M" | "M2" | "KG" | "KG*M2" | "N/M" | "N/M/SEC" | "RAD" => 1.0,
+        "IN" => 0.0254,
+        "FT" => 0.3048,
+        "FT2" => 0.09290304,
+        "IN3" => 1.6387064e-5,
+        "LBS" => 0.45359237,
+        "SLUG*FT2" => 1.3558179,
+        "LBS/FT" | "LBS/FT/SEC" => 14.593903,
+        "DEG" => deg_to_rad(1.0),
+        _ => return Err(format!("dont know the unit {}", unit)),
+    };
+    Ok(value * scale)
+}
+
+fn measured(parent: &Node, name: &str, default_unit: &str) -> Result<f64, String> {
+    let node = section(parent, name)?;
+    to_si(number(&node)?, node.attribute("unit").unwrap_or(default_unit))
+}
+
+fn location(node: &Node) -> Result<Vec3, String> {
+    // in the structural frame, which is x aft, y right, z up. the stock files split it into <x> <y> <z>
+    let split = ["x", "y", "z"].map(|axis| node.children().find(|child| child.has_tag_name(axis)));
+    let values = match split {
+        [Some(x), Some(y), Some(z)] => vec![number(&x)?, number(&y)?, number(&z)?],
+        _ => numbers(node)?,
+    };
+    match values[..] {
+        [x, y, z] => {
+            let unit = node.attribute("unit").unwrap_or("IN");
+            Ok(Vec3::new(to_si(x, unit)?, to_si(y, unit)?, to_si(z, unit)?))
+        },
+        _ => Err("a <location> needs three numbers".to_string()),
+    }
+}
+
+fn named_location(parent: &Node, name: &str) -> Result<Option<Vec3>, String> {
+    match parent.children().find(|node| node.has_tag_name("location") && node.attribute("name") == Some(name)) {
+        Some(node) => Ok(Some(location(&node)?)),
+        None => Ok(None),
+    }
+}
+
+fn to_body(structural: &Vec3, cg: &Vec3) -> Vec3 {
+    // the structural frame points backwards and to the right, ours is forwards and to the left
+    Vec3::new(-(structural.x - cg.x), -(structural.y - cg.y), structural.z - cg.z)
+}
+
+fn mass_balance(node: &Node, propulsion: Option<&Node>) -> Result<(MassProperties, Balance, Vec3), String> {
+    /*
+    the empty plane, with the pointmasses as stations and the tanks as tanks. everything ends up measured
+    from where the CG is with the load in the file, so thats what the reference point is
+     */
+    let empty_cg = named_location(node, "CG")?.ok_or("theres no CG <location> in the mass_balance")?;
+    let empty = measured(node, "emptywt", "LBS")?;
+    let mut stations = Vec::new();
+    for pointmass in node.children().filter(|node| node.has_tag_name("pointmass")) {
+        let position = named_location(&pointmass, "POINTMASS")?.ok_or("a <pointmass> needs a location")?;
+        let mass = measured(&pointmass, "weight", "LBS")?;
+        // jsbsim doesnt have placard limits, so anything up to twice whats in the file is allowed
+        stations.push((pointmass.attribute("name").unwrap_or("pointmass").to_string(), position, mass, 2.0 * mass));
+    }
+    let mut tanks = Vec::new();
+    if let Some(propulsion) = propulsion {
+        for (number, tank) in propulsion.children().filter(|node| node.has_tag_name("tank")).enumerate() {
+            let capacity = measured(&tank, "capacity", "LBS")?;
+            let fuel = measured(&tank, "contents", "LBS")?;
+            tanks.push((format!("tank {}", number), location(&section(&tank, "location")?)?, fuel, capacity));
+        }
+    }
+
+    let mass = empty + stations.iter().map(|station| station.2).sum::<f64>() + tanks.iter().map(|tank| tank.2).sum::<f64>();
+    let moment = stations.iter().chain(tanks.iter()).fold(empty_cg * empty, |sum, (_, position, part, _)| sum + &(*position * *part));
+    let reference = moment * (1.0 / mass);
+
+    // products of inertia change sign going from jsbsims z down body axes to our z up ones
+    let inertia_of = |name: &str| measured(node, name, "SLUG*FT2").or_else(|_| if name == "ixz" { Ok(0.0) } else { Err(format!("theres no <{}>", name)) });
+    let empty = MassProperties {
+        mass: empty,
+        cg: to_body(&empty_cg, &reference),
+        inertia: [inertia_of("ixx")?, inertia_of("iyy")?, inertia_of("izz")?, -inertia_of("ixz")?],
+    };
+    let balance = Balance {
+        datum: to_body(&Vec3::new(0.0, 0.0, 0.0), &reference),
+        stations: stations.into_iter().map(|(name, position, mass, max)| Station { name, position: to_body(&position, &reference), mass, max }).collect(),
+        tanks: tanks.into_iter().map(|(name, position, fuel, capacity)| Tank { name, position: to_body(&position, &reference), fuel, capacity }).collect(),
+        envelope: Vec::new(),
+    };
+    Ok((empty, balance, reference))
+}
+
+fn find_file(directory: &Path, name: &str) -> Result<PathBuf, String> {
+    // jsbsim looks next to the aircraft, in its Engines folder, then in the shared engine folder
+    let file = format!("{}.xml", name);
+    [directory.join(&file), directory.join("Engines").join(&file), directory.join("../../engine").join(&file)]
+        .into_iter()
+        .find(|path| path.exists())
+        .ok_or(format!("couldnt find {} anywhere", file))
+}
+
+fn read_file(directory: &Path, name: &str) -> Result<String, String> {
+    let path = find_file(directory, name)?;
+    std::fs::read_to_string(&path).map_err(|error| format!("couldnt read {}: {}", path.display(), error))
+}
+
+fn engine(propulsion: &Node, directory: &Path, cg: &Vec3) -> Result<(EngineDefinition, Propeller, Vec3), String> {
+    // only the one engine, the rest of the sim only knows how to fly singles
+    let node = section(propulsion, "engine")?;
+    let engine_name = node.attribute("file").ok_or("<engine> needs a file")?;
+    let thruster = section(&node, "thruster")?;
+    let thruster_name = thruster.attribute("file").ok_or("<thruster> needs a file")?;
+    let thrust_position = to_body(&location(&section(&thruster, "location")?)?, cg);
+
+    let text = read_file(directory, thruster_name)?;
+    let document = Document::parse(&text).map_err(|error| format!("{}: {}", thruster_name, error))?;
+    let prop = document.root_element();
+    if prop.tag_name().name() != "propeller" {
+        return Err(format!("{} is a <{}>, only fixed pitch propellers work", thruster_name, prop.tag_name().name()));
+    }
+    let coefficients = |name: &str| -> Result<Vec<(f64, f64)>, String> {
+        let table = prop.children().find(|node| node.has_tag_name("table") && node.attribute("name") == Some(name))
+            .ok_or(format!("{} has no {} table", thruster_name, name))?;
+        let data = numbers(&section(&table, "tableData")?)?;
+        if data.len() < 4 || data.len() % 2 != 0 {
+            return Err(format!("the {} table should be pairs of advance ratio and coefficient", name));
+        }
+        Ok(data.chunks(2).map(|pair| (pair[0], pair[1])).collect())
+    };
+    let propeller = Propeller {
+        diameter: measured(&prop, "diameter", "IN")?,
+        thrust_coefficient: coefficients("C_THRUST")?,
+        power_coefficient: coefficients("C_POWER")?,
+    };
+    let prop_inertia = measured(&prop, "ixx", "SLUG*FT2")?;
+
+    let text = read_file(directory, engine_name)?;
+    let document = Document::parse(&text).map_err(|error| format!("{}: {}", engine_name, error))?;
+    let piston = document.root_element();
+    if piston.tag_name().name() != "piston_engine" {
+        return Err(format!("{} is a <{}>, only piston engines work", engine_name, piston.tag_name().name()));
+    }
+    let plain = |name: &str, default: f64| match piston.children().find(|node| node.has_tag_name(name)) {
+        Some(node) => number(&node),
+        None => Ok(default),
+    };
+    let engine = EngineDefinition::rated(
+        measured(&piston, "displacement", "IN3")?,
+        plain("volumetric-efficiency", 0.85)?,
+        plain("maxrpm", 2700.0)?,
+        plain("maxhp", 180.0)? * HORSEPOWER,
+        prop_inertia,
+        plain("idlerpm", 600.0)?,
+    );
+    Ok((engine, propeller, thrust_position))
+}
+
+fn gear_legs(node: &Node, cg: &Vec3) -> Result<Vec<GearLeg>, String> {
+    // the STRUCTURE contacts are just for crashing on, theyre read separately for the touchdown monitor
+    let mut legs = Vec::new();
+    for contact in node.children().filter(|node| node.has_tag_name("contact") && node.attribute("type") == Some("BOGEY")) {
+        let max_steer = match contact.children().find(|node| node.has_tag_name("max_steer")) {
+            Some(node) => rad_to_deg(to_si(number(&node)?, node.attribute("unit").unwrap_or("DEG"))?),
+            None => 0.0,
+        };
+        let brake = match contact.children().find(|node| node.has_tag_name("brake_group")) {
+            Some(node) => match node.text().unwrap_or("").trim() {
+                "LEFT" => Brake::Left,
+                "RIGHT" => Brake::Right,
+                _ => Brake::None,
+            },
+            None => Brake::None,
+        };
+        legs.push(GearLeg {
+            position: to_body(&location(&section(&contact, "location")?)?, cg),
+            spring: measured(&contact, "spring_coeff", "LBS/FT")?,
+            damping: measured(&contact, "damping_coeff", "LBS/FT/SEC")?,
+            max_steer,
+            brake,
+        });
+    }
+    Ok(legs)
+}
+
+fn structure_contacts(node: &Node, cg: &Vec3) -> Result<Vec<Contact>, String> {
+    // jsbsim doesnt say what each one is, so it goes on the name
+    let mut contacts = Vec::new();
+    for contact in node.children().filter(|node| node.has_tag_name("contact") && node.attribute("type") == Some("STRUCTURE")) {
+        let name = contact.attribute("name").unwrap_or("").to_uppercase();
+        let cause = if name.contains("TIP") || name.contains("WING") {
+            CrashCause::WingStrike
+        } else if name.contains("TAIL") {
+            CrashCause::TailStrike
+        } else if name.contains("NOSE") || name.contains("PROP") || name.contains("SPINNER") {
+            CrashCause::PropStrike
+        } else {
+            CrashCause::BellyLanding
+        };
+        contacts.push(Contact { cause, position: to_body(&location(&section(&contact, "location")?)?, cg) });
+    }
+    Ok(contacts)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Input {
+    DynamicPressure,
+    Area,
+    Span,
+    Chord,
+    Alpha,
+    AlphaDegrees,
+    Beta,
+    BetaDegrees,
+    MagnitudeBeta,
+    HalfSpanOverAirspeed,
+    HalfChordOverAirspeed,
+    StallHysteresis,
+    HeightOverSpan,
+    RollRate,
+    PitchRate,
+    YawRate,
+    Elevator,
+    MagnitudeElevator,
+    LeftAileron,
+    RightAileron,
+    Rudder,
+    Flaps,
+    Airspeed,
+}
+
+impl Input {
+    fn from_property(name: &str) -> Option<Input> {
+        Some(match name {
+            "aero/qbar-psf" => Input::DynamicPressure,
+            "metrics/Sw-sqft" => Input::Area,
+            "metrics/bw-ft" => Input::Span,
+            "metrics/cbarw-ft" => Input::Chord,
+            "aero/alpha-rad" => Input::Alpha,
+            "aero/alpha-deg" => Input::AlphaDegrees,
+            "aero/beta-rad" => Input::Beta,
+            "aero/beta-deg" => Input::BetaDegrees,
+            "aero/mag-beta-rad" => Input::MagnitudeBeta,
+            "aero/bi2vel" => Input::HalfSpanOverAirspeed,
+            "aero/ci2vel" => Input::HalfChordOverAirspeed,
+            "aero/stall-hyst-norm" => Input::StallHysteresis,
+            "aero/h_b-mac-ft" | "aero/h_b-cg-ft" => Input::HeightOverSpan,
+            "velocities/p-aero-rad_sec" | "velocities/p-rad_sec" => Input::RollRate,
+            "velocities/q-aero-rad_sec" | "velocities/q-rad_sec" => Input::PitchRate,
+            "velocities/r-aero-rad_sec" | "velocities/r-rad_sec" => Input::YawRate,
+            "fcs/elevator-pos-rad" => Input::Elevator,
+            "fcs/mag-elevator-pos-rad" => Input::MagnitudeElevator,
+            "fcs/left-aileron-pos-rad" => Input::LeftAileron,
+            "fcs/right-aileron-pos-rad" => Input::RightAileron,
+            "fcs/rudder-pos-rad" => Input::Rudder,
+            "fcs/flap-pos-deg" => Input::Flaps,
+            "velocities/vt-fps" => Input::Airspeed,
+            _ => return None,
+        })
+    }
+}
+
+// a 1d or 2d table, the 1d ones just have the one column
+#[derive(Clone)]
+struct Table {
+    row: Box<Expression>,
+    column: Option<Box<Expression>>,
+    rows: Vec<f64>,
+    columns: Vec<f64>,
+    data: Vec<Vec<f64>>,
+}
+
+#[derive(Clone)]
+enum Expression {
+    Value(f64),
+    Input(Input, f64), // with a sign, jsbsim lets you write -fcs/elevator-pos-rad
+    Function(usize, f64), // another function thats already been worked out
+    Product(Vec<Expression>),
+    Sum(Vec<Expression>),
+    Difference(Vec<Expression>),
+    Quotient(Box<Expression>, Box<Expression>),
+    Pow(Box<Expression>, Box<Expression>),
+    Abs(Box<Expression>),
+    Sin(Box<Expression>),
+    Cos(Box<Expression>),
+    Sqrt(Box<Expression>),
+    Min(Vec<Expression>),
+    Max(Vec<Expression>),
+    Table(Table),
+}
+
+#[derive(Clone)]
+struct Function {
+    name: String,
+    axis: Option<usize>,
+    expression: Expression,
+}
+
+// the aerodynamics section, worked out from the function tables instead of our hand tuned coefficients
+#[derive(Clone)]
+pub struct Aerodynamics {
+    functions: Vec<Function>,
+    area: f64, // m^2
+    span: f64, // m
+    chord: f64, // m
+    aerorp: Vec3, // where the forces act, body coords from the reference point
+    pub stall_alpha: (f64, f64), // degrees, where the flow comes back and where it lets go
+    pub warnings: Vec<String>,
+}
+
+struct Parser {
+    names: HashMap<String, usize>,
+    warnings: Vec<String>,
+}
+
+impl Parser {
+    fn children(&mut self, node: &Node) -> Result<Vec<Expression>, String> {
+        node.children()
+            .filter(|child| child.is_element() && !child.has_tag_name("description"))
+            .map(|child| self.expression(&child))
+            .collect()
+    }
+
+    fn one(&mut self, node: &Node) -> Result<Box<Expression>, String> {
+        match self.children(node)?.pop() {
+            Some(expression) => Ok(Box::new(expression)),
+            None => Err(format!("<{}> needs something in it", node.tag_name().name())),
+        }
+    }
+
+    fn two(&mut self, node: &Node) -> Result<(Box<Expression>, Box<Expression>), String> {
+        let mut arguments = self.children(node)?;
+        if arguments.len() != 2 {
+            return Err(format!("<{}> needs two things in it", node.tag_name().name()));
+        }
+        let second = arguments.pop().unwrap();
+        Ok((Box::new(arguments.pop().unwrap()), Box::new(second)))
+    }
+
+    fn property(&mut self, text: &str) -> Expression {
+        let text = text.trim();
+        let (sign, name) = match text.strip_prefix('-') {
+            Some(name) => (-1.0, name),
+            None => (1.0, text),
+        };
+        if let Some(index) = self.names.get(name) {
+            return Expression::Function(*index, sign);
+        }
+        match Input::from_property(name) {
+            Some(input) => Expression::Input(input, sign),
+            None => {
+                let warning = format!("dont have {}, its going to be 0", name);
+                if !self.warnings.contains(&warning) {
+                    self.warnings.push(warning);
+                }
+                Expression::Value(0.0)
+            },
+        }
+    }
+
+    fn table(&mut self, node: &Node) -> Result<Table, String> {
+        let variables: Vec<Node> = node.children().filter(|child| child.has_tag_name("independentVar")).collect();
+        let data = section(node, "tableData")?;
+        let lines: Vec<Vec<f64>> = data.text().unwrap_or("").lines()
+            .map(|line| line.split_whitespace().map(|word| word.parse().map_err(|_| format!("{:?} in a table isnt a number", word))).collect())
+            .filter(|line: &Result<Vec<f64>, String>| !matches!(line, Ok(line) if line.is_empty()))
+            .collect::<Result<_, _>>()?;
+        if lines.is_empty() {
+            return Err("a <tableData> is empty".to_string());
+        }
+        match variables[..] {
+            [row] => {
+                if lines.iter().any(|line| line.len() != 2) {
+                    return Err("a 1d table wants two numbers a line".to_string());
+                }
+                in_order(lines.iter().map(|line| line[0]))?;
+                Ok(Table {
+                    row: Box::new(self.property(row.text().unwrap_or(""))),
+                    column: None,
+                    rows: lines.iter().map(|line| line[0]).collect(),
+                    columns: vec![0.0],
+                    data: lines.iter().map(|line| vec![line[1]]).collect(),
+                })
+            },
+            [first, second] => {
+                let (row, column) = if first.attribute("lookup") == Some("column") { (second, first) } else { (first, second) };
+                let (columns, rows) = lines.split_first().ok_or("a 2d table needs some data")?;
+                if rows.is_empty() || rows.iter().any(|line| line.len() != columns.len() + 1) {
+                    return Err("every row of a 2d table needs a value for each column".to_string());
+                }
+                in_order(rows.iter().map(|line| line[0]))?;
+                in_order(columns.iter().copied())?;
+                Ok(Table {
+                    row: Box::new(self.property(row.text().unwrap_or(""))),
+                    column: Some(Box::new(self.property(column.text().unwrap_or("")))),
+                    rows: rows.iter().map(|line| line[0]).collect(),
+                    columns: columns.clone(),
+                    data: rows.iter().map(|line| line[1..].to_vec()).collect(),
+                })
+            },
+            _ => Err("only 1d and 2d tables work".to_string()),
+        }
+    }
+
+    fn expression(&mut self, node: &Node) -> Result<Expression, String> {
+        Ok(match node.tag_name().name() {
+            "value" | "v" => Expression::Value(number(node)?),
+            "property" | "p" => self.property(node.text().unwrap_or("")),
+            "product" => Expression::Product(self.children(node)?),
+            "sum" => Expression::Sum(self.children(node)?),
+            "difference" => Expression::Difference(self.children(node)?),
+            "quotient" => {
+                let (top, bottom) = self.two(node)?;
+                Expression::Quotient(top, bottom)
+            },
+            "pow" => {
+                let (base, exponent) = self.two(node)?;
+                Expression::Pow(base, exponent)
+            },
+            "abs" => Expression::Abs(self.one(node)?),
+            "sin" => Expression::Sin(self.one(node)?),
+            "cos" => Expression::Cos(self.one(node)?),
+            "sqrt" => Expression::Sqrt(self.one(node)?),
+            "min" => Expression::Min(self.children(node)?),
+            "max" => Expression::Max(self.children(node)?),
+            "table" => Expression::Table(self.table(node)?),
+            other => return Err(format!("dont know how to do <{}>", other)),
+        })
+    }
+
+    fn function(&mut self, node: &Node, axis: Option<usize>, functions: &mut Vec<Function>) -> Result<(), String> {
+        let name = node.attribute("name").unwrap_or("").to_string();
+        let expression = *self.one(node).map_err(|error| format!("{}: {}", name, error))?;
+        self.names.insert(name.clone(), functions.len());
+        functions.push(Function { name, axis, expression });
+        Ok(())
+    }
+}
+
+fn in_order(keys: impl Iterator<Item = f64>) -> Result<(), String> {
+    // bracket only works if the breakpoints go up
+    let keys: Vec<f64> = keys.collect();
+    if keys.windows(2).any(|pair| pair[1] <= pair[0]) {
+        return Err("the breakpoints in a table have to go up".to_string());
+    }
+    Ok(())
+}
+
+fn bracket(keys: &[f64], x: f64) -> (usize, usize, f64) {
+    // which two breakpoints x is between and how far along, sticking at the ends like interpolate does.
+    // the table parser makes sure theres at least one
+    if x <= keys[0] {
+        return (0, 0, 0.0);
+    }
+    for i in 1..keys.len() {
+        if x <= keys[i] {
+            return (i - 1, i, (x - keys[i - 1]) / (keys[i] - keys[i - 1]));
+        }
+    }
+    (keys.len() - 1, keys.len() - 1, 0.0)
+}
+
+impl Aerodynamics {
+    fn parse(node: &Node, area: f64, span: f64, chord: f64, aerorp: Vec3) -> Result<Aerodynamics, String> {
+        let mut parser = Parser { names: HashMap::new(), warnings: Vec::new() };
+        let mut functions = Vec::new();
+        let mut stall_alpha = (10.0, 14.0);
+        for child in node.children().filter(|child| child.is_element()) {
+            match child.tag_name().name() {
+                "hysteresis_limits" => {
+                    let unit = child.attribute("unit").unwrap_or("DEG");
+                    stall_alpha = (
+                        rad_to_deg(to_si(number(&section(&child, "min")?)?, unit)?),
+                        rad_to_deg(to_si(number(&section(&child, "max")?)?, unit)?),
+                    );
+                },
+                "function" => parser.function(&child, None, &mut functions)?,
+                "axis" => {
+                    let name = child.attribute("name").unwrap_or("");
+                    let axis = match AXES.iter().position(|axis| *axis == name) {
+                        Some(axis) => axis,
+                        None => return Err(format!("the {} axis isnt one we do, it has to be the wind axes", name)),
+                    };
+                    for function in child.children().filter(|node| node.has_tag_name("function")) {
+                        parser.function(&function, Some(axis), &mut functions)?;
+                    }
+                },
+                _ => (),
+            }
+        }
+        Ok(Aerodynamics { functions, area, span, chord, aerorp, stall_alpha, warnings: parser.warnings })
+    }
+
+    fn input(&self, input: Input, inputs: &AeroInputs) -> f64 {
+        // turned into feet and jsbsims signs. jsbsim is x forward, y right, z down, so roll rate matches ours but pitch and
+        // yaw rate are the other way round. its controls are trailing edge down for the elevator and each aileron, and
+        // trailing edge left (nose left) for the rudder, so our up elevator and left roll flip and the rudder doesnt
+        let over_airspeed = |length: f64| if inputs.airspeed > 1.0 { length / (2.0 * inputs.airspeed) } else { 0.0 };
+        match input {
+            Input::DynamicPressure => inputs.dynamic_pressure / PASCALS_PER_PSF,
+            Input::Area => self.area / 0.09290304,
+            Input::Span => self.span / 0.3048,
+            Input::Chord => self.chord / 0.3048,
+            Input::Alpha => inputs.alpha,
+            Input::AlphaDegrees => rad_to_deg(inputs.alpha),
+            Input::Beta => inputs.beta,
+            Input::BetaDegrees => rad_to_deg(inputs.beta),
+            Input::MagnitudeBeta => inputs.beta.abs(),
+            Input::HalfSpanOverAirspeed => over_airspeed(self.span),
+            Input::HalfChordOverAirspeed => over_airspeed(self.chord),
+            Input::StallHysteresis => if inputs.stalled { 1.0 } else { 0.0 },
+            Input::HeightOverSpan => inputs.height.max(0.0) / self.span,
+            Input::RollRate => inputs.angular_rate.x,
+            Input::PitchRate => -inputs.angular_rate.y,
+            Input::YawRate => -inputs.angular_rate.z,
+            Input::Elevator => -inputs.elevator,
+            Input::MagnitudeElevator => inputs.elevator.abs(),
+            Input::LeftAileron => -inputs.aileron,
+            Input::RightAileron => inputs.aileron,
+            Input::Rudder => inputs.rudder,
+            Input::Flaps => inputs.flaps,
+            Input::Airspeed => inputs.airspeed / 0.3048,
+        }
+    }
+
+    fn evaluate(&self, expression: &Expression, inputs: &AeroInputs, done: &[f64]) -> f64 {
+        let all = |arguments: &Vec<Expression>| arguments.iter().map(|argument| self.evaluate(argument, inputs, done)).collect::<Vec<f64>>();
+        match expression {
+            Expression::Value(value) => *value,
+            Expression::Input(input, sign) => sign * self.input(*input, inputs),
+            Expression::Function(index, sign) => sign * done.get(*index).copied().unwrap_or(0.0),
+            Expression::Product(arguments) => arguments.iter().map(|argument| self.evaluate(argument, inputs, done)).product(),
+            Expression::Sum(arguments) => arguments.iter().map(|argument| self.evaluate(argument, inputs, done)).sum(),
+            Expression::Difference(arguments) => {
+                let values = all(arguments);
+                values.first().copied().unwrap_or(0.0) - values.iter().skip(1).sum::<f64>()
+            },
+            Expression::Quotient(top, bottom) => {
+                let bottom = self.evaluate(bottom, inputs, done);
+                if bottom == 0.0 { 0.0 } else { self.evaluate(top, inputs, done) / bottom }
+            },
+            Expression::Pow(base, exponent) => self.evaluate(base, inputs, done).powf(self.evaluate(exponent, inputs, done)),
+            Expression::Abs(argument) => self.evaluate(argument, inputs, done).abs(),
+            Expression::Sin(argument) => self.evaluate(argument, inputs, done).sin(),
+            Expression::Cos(argument) => self.evaluate(argument, inputs, done).cos(),
+            Expression::Sqrt(argument) => self.evaluate(argument, inputs, done).max(0.0).sqrt(),
+            Expression::Min(arguments) => all(arguments).into_iter().fold(f64::INFINITY, f64::min),
+            Expression::Max(arguments) => all(arguments).into_iter().fold(f64::NEG_INFINITY, f64::max),
+            Expression::Table(table) => {
+                let (r0, r1, along_rows) = bracket(&table.rows, self.evaluate(&table.row, inputs, done));
+                let (c0, c1, along_columns) = match &table.column {
+                    Some(column) => bracket(&table.columns, self.evaluate(column, inputs, done)),
+                    None => (0, 0, 0.0),
+                };
+                let at = |row: usize| table.data[row][c0] + (table.data[row][c1] - table.data[row][c0]) * along_columns;
+                at(r0) + (at(r1) - at(r0)) * along_rows
+            },
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn function(&self, name: &str, inputs: &AeroInputs) -> Option<f64> {
+        // what one of the named functions comes out as, for poking at a model
+        let mut done = Vec::with_capacity(self.functions.len());
+        for function in &self.functions {
+            done.push(self.evaluate(&function.expression, inputs, &done));
+            if function.name == name {
+                return done.last().copied();
+            }
+        }
+        None
+    }
+}
+
+impl AeroModel for Aerodynamics {
+    fn forces(&self, inputs: &AeroInputs) -> (Vec3, Vec3) {
+        // same as the rest of the free body diagram, about the reference point in body coords
+        let mut done = Vec::with_capacity(self.functions.len());
+        let mut axes = [0.0; 6];
+        for function in &self.functions {
+            let value = self.evaluate(&function.expression, inputs, &done);
+            if let Some(axis) = function.axis {
+                axes[axis] += value;
+            }
+            done.push(value);
+        }
+        let [drag, side, lift, roll, pitch, yaw] = axes;
+
+        let force = body_force(inputs.alpha, inputs.beta, drag, side, lift) * POUNDS_FORCE;
+        // the moments are about the aero reference point with jsbsims pitch and yaw the other way round
+        let moment = Vec3::new(roll, -pitch, -yaw) * FOOT_POUNDS + &self.aerorp.cross_product(&force);
+        (force, moment)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const C172X: &str = "aircraft/synthetic_jsbsim/aircraft/c172x/c172x.xml";
+
+    fn cruising(alpha: f64) -> AeroInputs {
+        AeroInputs {
+            density: 1.225,
+            dynamic_pressure: 0.5 * 1.225 * 40.0_f64.powf(2.0),
+            airspeed: 40.0,
+            alpha,
+            beta: 0.0,
+            angular_rate: Vec3::new(0.0, 0.0, 0.0),
+            elevator: 0.0,
+            aileron: 0.0,
+            rudder: 0.0,
+            flaps: 0.0,
+            stalled: false,
+            panels_stalled: [false; 2],
+            height: 1000.0,
+        }
+    }
+
+    #[test]
+    fn loads_the_c172x() {
+        let definition = load(C172X).unwrap();
+        assert_eq!(definition.name, "c172x");
+        // 1500 empty, two people and most of the fuel
+        assert!((definition.balance.loaded(&definition.mass).mass - 2120.0 * 0.45359237).abs() < 1.0);
+        assert!(definition.balance.loaded(&definition.mass).cg.magnitude() < 1e-9);
+        assert_eq!(definition.balance.stations.len(), 2);
+        assert_eq!(definition.balance.tanks[1].capacity, 150.0 * 0.45359237);
+        assert!((definition.geometry.span - 35.8 * 0.3048).abs() < 1e-9);
+        assert_eq!(definition.gear.legs.len(), 3);
+        let nose = &definition.gear.legs[0];
+        assert!(nose.max_steer == 10.0 && nose.position.x > 1.0 && nose.position.z < -1.0);
+        assert!(definition.gear.legs[1].brake == Brake::Left && definition.gear.legs[1].position.y > 0.0);
+        assert!(definition.geometry.thrust_position.x > 1.5);
+        assert_eq!(definition.propeller.thrust_coefficient[0], (0.0, 0.105));
+        assert_eq!(definition.wing.critical_alpha, 16.0);
+        // the tail skid and both tips, from the file rather than the 172
+        let causes: Vec<CrashCause> = definition.geometry.contacts.iter().map(|contact| contact.cause).collect();
+        assert_eq!(causes, [CrashCause::TailStrike, CrashCause::WingStrike, CrashCause::WingStrike]);
+        let tip = definition.geometry.contacts[1].position;
+        assert!((tip.y - 214.8 * 0.0254).abs() < 1e-9 && tip.z > 0.5);
+        // every property is known, but it owns up to what it borrowed
+        let warnings = definition.aero_tables.unwrap().warnings;
+        assert!(!warnings.iter().any(|warning| warning.starts_with("dont have")));
+        assert!(warnings.iter().any(|warning| warning.contains("[aero]")));
+        assert!(warnings.iter().any(|warning| warning.contains("fin")));
+        assert!(warnings.iter().any(|warning| warning.contains("wing lift curves")));
+    }
+
+    #[test]
+    fn tables_and_signs() {
+        let aero = load(C172X).unwrap().aero_tables.unwrap();
+        // straight out of the 2d table, halfway between two rows
+        let mut inputs = cruising(0.095);
+        assert!((aero.function("aero/coefficient/CLwbh", &inputs).unwrap() / (inputs.dynamic_pressure / PASCALS_PER_PSF * 174.0) - 0.78).abs() < 1e-3);
+        inputs.stalled = true;
+        assert!((aero.function("aero/coefficient/CLwbh", &inputs).unwrap() / (inputs.dynamic_pressure / PASCALS_PER_PSF * 174.0) - 0.755).abs() < 1e-3);
+
+        // about the same lift as our own model at a normal alpha, and its upwards
+        let (force, _) = aero.forces(&cruising(deg_to_rad(4.0)));
+        let ours = 0.5 * 1.225 * 40.0_f64.powf(2.0) * 16.17 * 0.8;
+        assert!(force.z > 0.7 * ours && force.z < 1.3 * ours);
+        assert!(force.x < 0.0);
+
+        check_signs(&aero);
+    }
+
+    fn check_signs(aero: &Aerodynamics) {
+        // up elevator pitches the nose up, which is negative about our y
+        let level = aero.forces(&cruising(deg_to_rad(4.0))).1;
+        let mut inputs = cruising(deg_to_rad(4.0));
+        inputs.elevator = deg_to_rad(10.0);
+        assert!(aero.forces(&inputs).1.y < level.y);
+        // positive aileron rolls left, which is negative about our x, and positive rudder yaws left
+        let mut inputs = cruising(deg_to_rad(4.0));
+        inputs.aileron = deg_to_rad(10.0);
+        assert!(aero.forces(&inputs).1.x < level.x);
+        let mut inputs = cruising(deg_to_rad(4.0));
+        inputs.rudder = deg_to_rad(10.0);
+        assert!(aero.forces(&inputs).1.z > level.z);
+        // and turning about any axis gets damped
+        for axis in 0..3 {
+            let mut inputs = cruising(deg_to_rad(4.0));
+            let mut rate = [0.0; 3];
+            rate[axis] = 0.5;
+            inputs.angular_rate = Vec3::new(rate[0], rate[1], rate[2]);
+            let moment = aero.forces(&inputs).1;
+            let change = [moment.x - level.x, moment.y - level.y, moment.z - level.z];
+            assert!(change[axis] < 0.0, "turning about axis {} isnt damped", axis);
+        }
+    }
+
+    #[test]
+    fn signs_match_the_real_c172x() {
+        // the one in aircraft is synthetic, so the sign mapping only gets checked against real data if theres a jsbsim
+        // checkout to read, eg JSBSIM_ROOT=~/jsbsim cargo test
+        let root = match std::env::var("JSBSIM_ROOT") {
+            Ok(root) => root,
+            Err(_) => {
+                println!("JSBSIM_ROOT isnt set, not checking against the real c172x");
+                return;
+            },
+        };
+        let definition = load(&format!("{}/aircraft/c172x/c172x.xml", root)).unwrap();
+        assert_eq!(definition.name, "c172x");
+        check_signs(&definition.aero_tables.unwrap());
+    }
+
+    #[test]
+    fn complains_about_things_it_cant_do() {
+        let text = std::fs::read_to_string(C172X).unwrap();
+        let directory = Path::new(C172X).parent().unwrap();
+        assert!(parse(&text.replace("<product>", "<integral>").replace("</product>", "</integral>"), directory).is_err());
+        assert!(parse(&text.replace("eng_io320", "eng_missing"), directory).is_err());
+        assert!(parse("<not_a_plane/>", directory).is_err());
+        // a property we dont have still loads, it just says so
+        let known = parse(&text, directory).unwrap().aero_tables.unwrap().warnings.len();
+        let unknown = parse(&text.replace("aero/beta-rad", "aero/something-else"), directory).unwrap();
+        assert_eq!(unknown.aero_tables.unwrap().warnings.len(), known + 1);
+    }
+
+    #[test]
+    fn tables_have_to_have_something_in_them() {
+        let text = std::fs::read_to_string(C172X).unwrap();
+        let directory = Path::new(C172X).parent().unwrap();
+        let start = text.find("<tableData>").unwrap() + "<tableData>".len();
+        let end = text.find("</tableData>").unwrap();
+        let empty = format!("{}{}", &text[..start], &text[end..]);
+        assert!(parse(&empty, directory).err().unwrap().contains("empty"));
+        let backwards = text.replacen("0.0   1.203", "2.0   1.203", 1);
+        assert!(parse(&backwards, directory).err().unwrap().contains("go up"));
+    }
+
+    #[test]
+    fn borrows_the_172s_contacts_if_it_has_to() {
+        let text = std::fs::read_to_string(C172X).unwrap();
+        let directory = Path::new(C172X).parent().unwrap();
+        let definition = parse(&text.replace("\"STRUCTURE\"", "\"NOTHING\""), directory).unwrap();
+        assert_eq!(definition.geometry.contacts.len(), AircraftDefinition::cessna().geometry.contacts.len());
+        assert!(definition.aero_tables.unwrap().warnings.iter().any(|warning| warning.contains("STRUCTURE")));
+    }
+}
@@ -0,0 +1,79 @@
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+
+// writes the black box as one json object, and makes sure it gets closed off however the flight ends
+pub struct Logger {
+    file: File,
+    entries: usize,
+    outcomes: Vec<String>, // landings and crashes, they go at the end
+    finished: bool,
+}
+
+impl Logger {
+    pub fn create(path: &str) -> std::io::Result<Logger> {
+        let mut file = OpenOptions::new().write(true).create(true).truncate(true).open(path)?;
+        file.write_all(b"{\"data\": [")?;
+        Ok(Logger {
+            file,
+            entries: 0,
+            outcomes: Vec::new(),
+            finished: false,
+        })
+    }
+
+    pub fn write(&mut self, entry: &str) {
+        if self.finished {
+            return;
+        }
+        // commas go before so theres never one hanging off the end
+        let separator = if self.entries == 0 { "\n" } else { ",\n" };
+        self.file.write_all(separator.as_bytes()).unwrap();
+        self.file.write_all(entry.as_bytes()).unwrap();
+        self.entries += 1;
+    }
+
+    pub fn record(&mut self, outcome: String) {
+        self.outcomes.push(outcome);
+    }
+
+    pub fn finish(&mut self) -> std::io::Result<()> {
+        if self.finished {
+            return Ok(());
+        }
+        self.finished = true;
+        let ending = format!("\n], \"outcomes\": [{}]}}\n", self.outcomes.join(", "));
+        self.file.write_all(ending.as_bytes())?;
+        self.file.flush()
+    }
+}
+
+impl Drop for Logger {
+    fn drop(&mut self) {
+        // closing the window still leaves a log you can load, and if the disk wont take it theres nothing left to do but say so
+        if let Err(error) = self.finish() {
+            eprintln!("couldnt close the log: {}", error);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn closes_the_json() {
+        let path = std::env::temp_dir().join("flight_logger_test.json");
+        let path = path.to_str().unwrap();
+        {
+            let mut logger = Logger::create(path).unwrap();
+            logger.write("{\"a\": 1}");
+            logger.write("{\"a\": 2}");
+            logger.record("{\"landed\": true}".to_string());
+            logger.finish().unwrap();
+            logger.write("{\"a\": 3}");
+        }
+        let written = std::fs::read_to_string(path).unwrap();
+        let _ = std::fs::remove_file(path);
+        assert_eq!(written, "{\"data\": [\n{\"a\": 1},\n{\"a\": 2}\n], \"outcomes\": [{\"landed\": true}]}\n");
+    }
+}
@@ -1,24 +1,65 @@
 mod camera;
+mod clock;
 mod common_math;
 mod state;
+mod touchdown;
+mod trim;
+mod aero;
+mod eigen;
+mod modes;
+mod turbulence;
+mod wind;
+mod wing;
 mod aircraft;
+mod atmosphere;
+mod balance;
+mod controls;
+mod definition;
+mod engine;
+mod fin;
+mod flaps;
+mod fuel;
+mod gear;
+mod integrator;
+mod jsbsim;
+mod logger;
+mod propeller;
 mod displays;
 
-use std::{fs::OpenOptions, io::Write};
-use chrono::Utc;
-
 use displays::{Dial, Gauge};
 use eframe::egui;
 
+const LOG_PATH: &str = "log.json";
+// what G throws at you, a 1-cosine updraft peaking at 6 m/s over about 2 seconds of flying
+const GUST: common_math::Vec3 = common_math::Vec3 { x: 0.0, y: 0.0, z: 6.0 };
+const GUST_LENGTH: f64 = 100.0;
+
 fn main() {
-    let _ = std::fs::remove_file("log.json");
-    let mut file = OpenOptions::new().append(true).create(true).open("log.json").unwrap();
-    let _ = file.write("{\"data\": [".as_bytes());
+    // cargo run -- path/to/plane.toml (or a jsbsim .xml), or just the built in 172 if theres nothing there
+    let definition = match std::env::args().nth(1) {
+        Some(path) => match definition::AircraftDefinition::load(&path) {
+            Ok(definition) => definition,
+            Err(error) => {
+                eprintln!("{}", error);
+                std::process::exit(1);
+            },
+        },
+        None => definition::AircraftDefinition::cessna(),
+    };
+    let loading = definition.balance.loaded(&definition.mass);
+    for problem in definition.balance.problems(&loading) {
+        eprintln!("weight and balance: {}", problem);
+    }
+    if let Some(tables) = &definition.aero_tables {
+        for warning in &tables.warnings {
+            eprintln!("{}", warning);
+        }
+    }
 	let options = eframe::NativeOptions::default();
 	eframe::run_native(
-		"My egui App",
+		&definition.name.clone(),
 		options,
-		Box::new(|_cc| Ok(Box::new(App::with_file(file)))),
+		Box::new(|_cc| Ok(Box::new(App::with_logger(logger::Logger::create(LOG_PATH).unwrap(), definition)))),
     ).unwrap();
     println!("done");
 }
@@ -26,11 +67,37 @@ fn main() {
 struct App{
     camera: camera::Camera,
     aircraft: aircraft::Aircraft,
-    logger: std::fs::File,
+    definition: definition::AircraftDefinition, // kept to build a fresh one on restart
+    clock: clock::Clock,
+    previous_state: state::State, // one physics step behind, to draw in between
+    logger: logger::Logger,
+    outcome: Option<touchdown::Outcome>, // the last landing, or the crash that ended it
+    wind: [wind::WindLayer; 2], // at the surface and aloft, kept through a restart
+    trim_target: trim::Target, // what restart trims the plane for, and what the trim button goes for
+    trimmed: Result<trim::Trim, String>, // how the last go at trimming went
+    modes: Option<Result<Vec<modes::Mode>, String>>, // what the modal analysis found, if its been asked for
+    derivatives: bool, // flying on the stability derivatives rather than the wing and fin, kept through a restart
     velocity_dial: displays::Dial,
     altitude_dial: displays::Dial,
     climb_rate_dial: displays::Dial,
     throttle_gauge: displays::Gauge,
+    rpm_dial: displays::Dial,
+    manifold_dial: displays::Dial,
+    fuel_flow_gauge: displays::Gauge,
+    mixture_gauge: displays::Gauge,
+}
+
+fn calm_layers() -> [wind::WindLayer; 2] {
+    // the surface one is what the tower would report, the upper one is about where the cumulus sit
+    [
+        wind::WindLayer { altitude: 10.0, from: 0.0, speed: 0.0 },
+        wind::WindLayer { altitude: 900.0, from: 0.0, speed: 0.0 },
+    ]
+}
+
+fn cruise() -> trim::Target {
+    // straight and level at about 100 knots
+    trim::Target::new(50.0, 0.0, 0.0)
 }
 
 impl Default for App {
@@ -38,49 +105,168 @@ impl Default for App {
         Self{
             camera: camera::Camera::new(),
             aircraft: aircraft::Aircraft::new(),
-            logger: std::fs::File::open("thing").unwrap(),
+            definition: definition::AircraftDefinition::cessna(),
+            clock: clock::Clock::new(),
+            previous_state: state::State::runway(),
+            logger: logger::Logger::create(LOG_PATH).unwrap(),
+            outcome: None,
+            wind: calm_layers(),
+            trim_target: cruise(),
+            trimmed: Err("not trimmed yet".to_string()),
+            modes: None,
+            derivatives: true,
             velocity_dial: displays::Dial::test(),
             altitude_dial: displays::Dial::test(),
             climb_rate_dial: displays::Dial::test(),
             throttle_gauge: displays::Gauge::test(),
+            rpm_dial: displays::Dial::test(),
+            manifold_dial: displays::Dial::test(),
+            fuel_flow_gauge: displays::Gauge::test(),
+            mixture_gauge: displays::Gauge::test(),
         }
     }
 }
 
 impl eframe::App for App {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        let time_start = Utc::now();
-        let dt = 1.0/50.0; // time to render in seconds
-        self.run_physics(dt);
+        let (steps, dt) = self.clock.tick(); // dt is the real frame time, the controls move by how long the keys were held
+        if let Some(touchdown::Outcome::Crashed(crash)) = self.outcome {
+            self.crash_screen(ctx, &crash);
+            return;
+        }
         egui::CentralPanel::default().show(ctx, |ui| {
-            let _ = ctx.input(|state|{
+            ctx.input(|state|{
+                let mut rolling = false;
+                let mut yawing = false;
+                self.aircraft.gear.left_brake = 0.0;
+                self.aircraft.gear.right_brake = 0.0;
                 for key_code in state.keys_down.clone() {
                     match key_code {
-                        egui::Key::W => self.aircraft.pitch_by(-20.0 * dt),
-                        egui::Key::S => self.aircraft.pitch_by(20.0 * dt),
-                        egui::Key::K => self.aircraft.pitch_by(1.0 * dt),
-                        egui::Key::I => self.aircraft.pitch_by(-1.0 * dt),
-                        egui::Key::Q => self.aircraft.state.pointing_global.roll += 1.0,
-                        egui::Key::E => self.aircraft.state.pointing_global.roll += -1.0,
-                        egui::Key::A => self.aircraft.yaw_by(10.0 * dt),
-                        egui::Key::D => self.aircraft.yaw_by(-10.0 * dt),
+                        egui::Key::W => self.aircraft.controls.elevator_by(-30.0 * dt),
+                        egui::Key::S => self.aircraft.controls.elevator_by(30.0 * dt),
+                        egui::Key::K => self.aircraft.controls.elevator_by(2.0 * dt),
+                        egui::Key::I => self.aircraft.controls.elevator_by(-2.0 * dt),
+                        egui::Key::L => self.aircraft.controls.trim_by(5.0 * dt),
+                        egui::Key::O => self.aircraft.controls.trim_by(-5.0 * dt),
+                        egui::Key::Q => {
+                            self.aircraft.controls.aileron_by(40.0 * dt);
+                            rolling = true;
+                        },
+                        egui::Key::E => {
+                            self.aircraft.controls.aileron_by(-40.0 * dt);
+                            rolling = true;
+                        },
+                        egui::Key::A => {
+                            self.aircraft.controls.rudder_by(30.0 * dt);
+                            yawing = true;
+                        },
+                        egui::Key::D => {
+                            self.aircraft.controls.rudder_by(-30.0 * dt);
+                            yawing = true;
+                        },
+                        egui::Key::B => {
+                            self.aircraft.gear.left_brake = 1.0;
+                            self.aircraft.gear.right_brake = 1.0;
+                        },
+                        egui::Key::Comma => self.aircraft.gear.left_brake = 1.0,
+                        egui::Key::Period => self.aircraft.gear.right_brake = 1.0,
                         egui::Key::Z => self.aircraft.throttle_by(5.0 * dt),
                         egui::Key::X => self.aircraft.throttle_by(-5.0 * dt),
+                        egui::Key::R => self.aircraft.engine.mixture = (self.aircraft.engine.mixture + 0.2 * dt).min(1.0),
+                        egui::Key::F => self.aircraft.engine.mixture = (self.aircraft.engine.mixture - 0.2 * dt).max(0.0),
                         _ => (),
                     }
                 }
+                if state.key_pressed(egui::Key::N) {
+                    self.aircraft.flaps.lever_down();
+                }
+                if state.key_pressed(egui::Key::M) {
+                    self.aircraft.flaps.lever_up();
+                }
+                if state.key_pressed(egui::Key::V) {
+                    self.aircraft.fuel.selector = self.aircraft.fuel.selector.next();
+                }
+                if state.key_pressed(egui::Key::T) {
+                    self.aircraft.turbulence.intensity = self.aircraft.turbulence.intensity.next();
+                }
+                if state.key_pressed(egui::Key::G) {
+                    self.aircraft.turbulence.gust(GUST, GUST_LENGTH);
+                }
+                if !rolling {
+                    self.aircraft.controls.centre_aileron(40.0 * dt);
+                }
+                if !yawing {
+                    self.aircraft.controls.centre_rudder(30.0 * dt);
+                }
             });
+            let view = self.run_physics(steps);
             ui.horizontal(|ui| {
                 ui.vertical( |ui| {
-                    ui.add(egui::Image::from_texture(&ctx.load_texture("siulator",self.camera.render(self.aircraft.state.velocity.angle_with_horizon(), 0.0), Default::default())));
+                    ui.add(egui::Image::from_texture(&ctx.load_texture("siulator",self.camera.render(view.velocity.angle_with_horizon(), 0.0), Default::default())));
                     ui.horizontal(|ui| {
-                        self.velocity_dial.draw(ui, self.aircraft.state.velocity.magnitude());
-                        self.altitude_dial.draw(ui, self.aircraft.state.position.z);
-                        self.climb_rate_dial.draw(ui, self.aircraft.state.velocity.z);
+                        self.velocity_dial.draw(ui, view.airspeed());
+                        self.altitude_dial.draw(ui, view.position.z);
+                        self.climb_rate_dial.draw(ui, view.velocity.z);
                         self.throttle_gauge.draw(ui, self.aircraft.throttle_percent);
+                        self.mixture_gauge.draw(ui, self.aircraft.engine.mixture);
+                        self.rpm_dial.draw(ui, self.aircraft.engine.rpm);
+                        self.manifold_dial.draw(ui, self.aircraft.engine.manifold_pressure_inhg());
+                        self.fuel_flow_gauge.draw(ui, self.aircraft.engine.fuel_flow_litres_per_hour());
+                    });
+                });
+                ui.vertical(|ui| {
+                    ui.label(format!("roll: {}", view.pointing_global().roll));
+                    ui.label(format!("elevator: {:.1}", common_math::rad_to_deg(self.aircraft.controls.elevator_angle())));
+                    ui.label(format!("trim tab: {:.1}", common_math::rad_to_deg(self.aircraft.controls.trim)));
+                    ui.label(format!("aileron: {:.1}", common_math::rad_to_deg(self.aircraft.controls.aileron)));
+                    ui.label(format!("rudder: {:.1}", common_math::rad_to_deg(self.aircraft.controls.rudder)));
+                    ui.label(format!("flaps: {:.0}", self.aircraft.flaps.position));
+                    ui.label(format!("fuel selector: {}", self.aircraft.fuel.selector.name()));
+                    if self.aircraft.engine.starved {
+                        ui.colored_label(egui::Color32::RED, "FUEL STARVATION");
+                    }
+                    self.weight_and_balance(ui);
+                    self.wind_settings(ui);
+                    self.trim_settings(ui);
+                    if self.aircraft.gear.left_brake > 0.0 || self.aircraft.gear.right_brake > 0.0 {
+                        ui.label("BRAKES");
+                    }
+                    if let Some(touchdown::Outcome::Landed(report)) = self.outcome {
+                        ui.label(format!("landed: {}", report.rating()));
+                        ui.label(format!("sink {:.1} m/s, {:.1} g", report.sink_rate, report.g_load));
+                        ui.label(format!("bank {:.1}, pitch {:.1}, {:.1} m/s", report.bank, report.pitch, report.groundspeed));
+                        if ui.button("restart").clicked() {
+                            self.restart();
+                        }
+                    }
+                    for integrator in [
+                        integrator::Integrator::Euler,
+                        integrator::Integrator::SemiImplicitEuler,
+                        integrator::Integrator::RungeKutta4,
+                        integrator::Integrator::DormandPrince { tolerance: 1e-6 },
+                    ] {
+                        ui.radio_value(&mut self.aircraft.integrator, integrator, integrator.name());
+                    }
+                    egui::CollapsingHeader::new("left turning tendencies").show(ui, |ui| {
+                        let effects = &mut self.aircraft.propeller_effects;
+                        ui.checkbox(&mut effects.torque, "engine torque");
+                        ui.checkbox(&mut effects.p_factor, "p factor");
+                        ui.checkbox(&mut effects.slipstream, "spiralling slipstream");
+                        ui.checkbox(&mut effects.gyroscopic, "gyroscopic precession");
                     });
+                    // only if the plane came with a set, it goes over to the wing and fin (or the jsbsim tables) when its off
+                    if self.definition.derivatives.is_some() && ui.checkbox(&mut self.derivatives, "fly on the stability derivatives").on_hover_text("theres no stall asymmetry in these so it wont spin, untick it for the wing and fin to spin it").changed() {
+                        self.aircraft.aero_model = self.definition.aero_model(self.derivatives);
+                    }
+                    if self.aircraft.wing.is_stalling(self.aircraft.get_alpha()) {
+                        ui.colored_label(egui::Color32::RED, "STALL");
+                    }
+                    if self.aircraft.flaps.damaged {
+                        ui.colored_label(egui::Color32::RED, "FLAPS DAMAGED");
+                    } else if self.aircraft.flaps.overspeed(self.aircraft.equivalent_airspeed()) {
+                        ui.colored_label(egui::Color32::YELLOW, "FLAP OVERSPEED");
+                    }
                 });
-                ui.label(format!("roll: {}", self.aircraft.state.pointing_global.roll));
                 /*ui.vertical(|ui|{
                     ui.label("velocity:    ");
                     ui.label("altitude:    ");
@@ -94,47 +280,218 @@ impl eframe::App for App {
                     ui.label(format!("{:.2}", self.aircraft.state.velocity.magnitude()));
                     ui.label(format!("{:.2}", self.aircraft.state.position.z));
                     ui.label(format!("{:.1}", self.aircraft.throttle_percent));
-                    ui.label(format!("{:.2}", self.aircraft.state.pointing_global.altitude));
-                    ui.label(format!("{:.2}", self.aircraft.state.pointing_global.roll));
-                    ui.label(format!("{:.1}", self.aircraft.state.pointing_global.as_vec3().angle_with(&self.aircraft.state.velocity) * 57.3));
+                    ui.label(format!("{:.2}", self.aircraft.state.pointing_global().altitude));
+                    ui.label(format!("{:.2}", self.aircraft.state.pointing_global().roll));
+                    ui.label(format!("{:.1}", self.aircraft.state.pointing_global().as_vec3().angle_with(&self.aircraft.state.velocity) * 57.3));
                     ui.label(format!("{:.3}", self.aircraft.state.velocity.z));
                 }); */
             });
         });
-        self.logger.write(self.aircraft.state.log().as_bytes()).unwrap();
-        self.logger.write(b",\n").unwrap();
         ctx.request_repaint();
-        let time_end = Utc::now();
-        let dt_actual = time_end - time_start;
-        //println!("{:?}", (time_end - time_start).num_milliseconds());
-        if let Some(time) = ((dt * 1e9) as u64).checked_sub(dt_actual.num_nanoseconds().unwrap() as u64) {
-            std::thread::sleep(std::time::Duration::from_nanos(time));
-        } else {
-            println!("frame took too long to render!");
-        }
     }
 }
 
 impl App {
-    fn run_physics(&mut self, dt: f64) {
-        self.aircraft.do_step(dt);
-        self.camera.euler = self.aircraft.state.pointing_global;
-        self.camera.position = self.aircraft.state.position;
+    fn run_physics(&mut self, steps: usize) -> state::State {
+        // catch the sim up to real time, then hand back where the plane is right now for drawing
+        for _ in 0..steps {
+            self.previous_state = self.aircraft.state;
+            let outcome = self.aircraft.do_step(self.clock.step);
+            self.logger.write(&self.aircraft.log());
+            if let Some(outcome) = outcome {
+                self.logger.record(outcome.jsonify());
+                self.outcome = Some(outcome);
+                if let touchdown::Outcome::Crashed(_) = outcome {
+                    if let Err(error) = self.logger.finish() {
+                        eprintln!("couldnt close the log: {}", error);
+                    }
+                    break;
+                }
+            }
+        }
+        let view = self.previous_state.interpolate(&self.aircraft.state, self.clock.blend());
+        self.camera.euler = view.pointing_global();
+        self.camera.position = view.position;
+        view
+    }
+
+    fn weight_and_balance(&mut self, ui: &mut egui::Ui) {
+        // the loading can be changed here, and it sticks through a restart
+        ui.label(format!("mass: {:.0}kg, cg: {:.3}m, fuel: {:.0}kg", self.aircraft.mass(), self.aircraft.cg_arm(), self.aircraft.balance.fuel()));
+        for tank in &self.aircraft.balance.tanks {
+            ui.label(format!("{} tank: {:.1}kg", tank.name, tank.fuel));
+        }
+        for problem in self.aircraft.loading_problems() {
+            ui.colored_label(egui::Color32::RED, format!("W&B: {}", problem));
+        }
+        let mut changed = false;
+        egui::CollapsingHeader::new("weight and balance").show(ui, |ui| {
+            for station in self.aircraft.balance.stations.iter_mut() {
+                // a bit past the limit so you can see what overloading it does
+                changed |= ui.add(egui::Slider::new(&mut station.mass, 0.0..=1.5 * station.max).text(&station.name)).changed();
+            }
+            for tank in self.aircraft.balance.tanks.iter_mut() {
+                changed |= ui.add(egui::Slider::new(&mut tank.fuel, 0.0..=tank.capacity).text(format!("{} tank", tank.name))).changed();
+            }
+        });
+        if changed {
+            self.aircraft.update_mass();
+            self.definition.balance = self.aircraft.balance.clone();
+        }
+    }
+
+    fn wind_settings(&mut self, ui: &mut egui::Ui) {
+        let state = &self.aircraft.state;
+        ui.label(format!("airspeed: {:.1} m/s, groundspeed: {:.1} m/s", state.airspeed(), state.groundspeed()));
+        let wind = state.wind;
+        ui.label(format!("wind here: {:.1} m/s", wind.magnitude()));
+        let mut changed = false;
+        egui::CollapsingHeader::new("wind").show(ui, |ui| {
+            for (layer, name) in self.wind.iter_mut().zip(["surface", "aloft"]) {
+                changed |= ui.add(egui::Slider::new(&mut layer.from, 0.0..=360.0).text(format!("{} from", name))).changed();
+                changed |= ui.add(egui::Slider::new(&mut layer.speed, 0.0..=30.0).text(format!("{} m/s", name))).changed();
+            }
+        });
+        if changed {
+            self.aircraft.wind = wind::Wind::new(self.wind.to_vec());
+        }
+        // a hot day or a low pressure thins the air out, the engine and the wing both feel it
+        egui::CollapsingHeader::new("temperature and pressure").show(ui, |ui| {
+            let atmosphere = &mut self.aircraft.atmosphere;
+            ui.add(egui::Slider::new(&mut atmosphere.temperature_offset, -30.0..=30.0).text("ISA + K"));
+            let mut qnh = atmosphere.sea_level_pressure / 100.0;
+            if ui.add(egui::Slider::new(&mut qnh, 950.0..=1050.0).text("QNH hPa")).changed() {
+                atmosphere.sea_level_pressure = qnh * 100.0;
+            }
+        });
+        ui.label(format!("density altitude: {:.0} m", self.aircraft.atmosphere.density_altitude(state.position.z)));
+        ui.horizontal(|ui| {
+            ui.label("turbulence:");
+            for intensity in [turbulence::Intensity::None, turbulence::Intensity::Light, turbulence::Intensity::Moderate, turbulence::Intensity::Severe] {
+                ui.radio_value(&mut self.aircraft.turbulence.intensity, intensity, intensity.name());
+            }
+        });
+        if ui.button("gust").clicked() {
+            self.aircraft.turbulence.gust(GUST, GUST_LENGTH);
+        }
+    }
+
+    fn trim_settings(&mut self, ui: &mut egui::Ui) {
+        // restart comes back trimmed for this, or the button trims it for this right where it is
+        egui::CollapsingHeader::new("trim").show(ui, |ui| {
+            let target = &mut self.trim_target;
+            ui.add(egui::Slider::new(&mut target.airspeed, 25.0..=70.0).text("airspeed m/s"));
+            ui.add(egui::Slider::new(&mut target.climb_rate, -10.0..=10.0).text("climb m/s"));
+            ui.add(egui::Slider::new(&mut target.bank, -60.0..=60.0).text("bank (left)"));
+            if ui.button("trim it").clicked() {
+                self.trimmed = trim::solve(&mut self.aircraft, &self.trim_target);
+                self.previous_state = self.aircraft.state;
+            }
+            // this trims it too, theres nothing to linearise about otherwise
+            if ui.button("find the modes").clicked() {
+                self.modes = Some(modes::analyse(&mut self.aircraft, &self.trim_target).map(|(trim, modes)| {
+                    self.trimmed = Ok(trim);
+                    modes
+                }));
+                self.previous_state = self.aircraft.state;
+            }
+            match &self.modes {
+                Some(Ok(modes)) => for mode in modes {
+                    ui.label(mode.describe());
+                    if let Some(reference) = mode.reference() {
+                        ui.small(reference);
+                    }
+                },
+                Some(Err(problem)) => {
+                    ui.colored_label(egui::Color32::YELLOW, format!("no modes: {}", problem));
+                },
+                None => {},
+            }
+        });
+        match &self.trimmed {
+            Ok(trim) => {
+                ui.label(format!("trimmed at {:.1} pitch, {:.1} alpha, {:.1} sideslip", trim.pitch, trim.alpha, trim.sideslip));
+                ui.label(format!("elevator {:.1}, aileron {:.1}, rudder {:.1}", trim.elevator, trim.aileron, trim.rudder));
+                ui.label(format!("throttle {:.0}% at {:.0} rpm", trim.throttle * 100.0, trim.rpm));
+            },
+            Err(problem) => {
+                ui.colored_label(egui::Color32::YELLOW, format!("no trim: {}", problem));
+            },
+        }
+    }
+
+    fn crash_screen(&mut self, ctx: &egui::Context, crash: &touchdown::CrashEvent) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.heading("CRASHED");
+            ui.label(format!("you {}", crash.cause.describe()));
+            ui.label(format!("sink rate: {:.1} m/s", crash.report.sink_rate));
+            ui.label(format!("load: {:.1} g", crash.report.g_load));
+            ui.label(format!("bank: {:.1}, pitch: {:.1}", crash.report.bank, crash.report.pitch));
+            ui.label(format!("groundspeed: {:.1} m/s", crash.report.groundspeed));
+            ui.label(format!("at x {:.0} y {:.0}", crash.report.position.x, crash.report.position.y));
+            if ui.button("restart").clicked() {
+                self.restart();
+            }
+        });
+    }
+
+    fn restart(&mut self) {
+        // the old log gets closed off first, then a fresh one starts over the top of it
+        if let Err(error) = self.logger.finish() {
+            eprintln!("couldnt close the log: {}", error);
+        }
+        self.logger = logger::Logger::create(LOG_PATH).unwrap();
+        let integrator = self.aircraft.integrator;
+        let intensity = self.aircraft.turbulence.intensity;
+        let propeller_effects = self.aircraft.propeller_effects;
+        let (temperature_offset, sea_level_pressure) = (self.aircraft.atmosphere.temperature_offset, self.aircraft.atmosphere.sea_level_pressure);
+        self.aircraft = aircraft::Aircraft::from_definition(&self.definition).airborne(state::State::flying_high());
+        self.aircraft.integrator = integrator;
+        self.aircraft.wind = wind::Wind::new(self.wind.to_vec());
+        self.aircraft.turbulence.intensity = intensity;
+        self.aircraft.propeller_effects = propeller_effects;
+        self.aircraft.atmosphere = atmosphere::Atmosphere::with_offsets(temperature_offset, sea_level_pressure);
+        self.aircraft.aero_model = self.definition.aero_model(self.derivatives);
+        // if it cant be trimmed for that it just starts off with the old guess
+        self.trimmed = trim::solve(&mut self.aircraft, &self.trim_target);
+        self.previous_state = self.aircraft.state;
+        self.clock = clock::Clock::new();
+        self.outcome = None;
     }
     
-    fn with_file(file: std::fs::File) -> App {
+    fn with_logger(logger: logger::Logger, definition: definition::AircraftDefinition) -> App {
         let velocity_dial = Dial::new("vel".to_string(), "m/s".to_string(), 80.0, 0.0);
         let altitude_dial = Dial::new("alt".to_string(), "m".to_string(), 400.0, 0.0);
         let climb_rate_dial = Dial::new("v_z".to_string(), "m/s".to_string(), -10.0, 10.0);
         let throttle_gauge = Gauge::new("throttle".to_string(), "%".to_string() , 1.0, 0.0);
+        let mixture_gauge = Gauge::new("mixture".to_string(), "%".to_string(), 1.0, 0.0);
+        let rpm_dial = Dial::new("rpm".to_string(), "rpm".to_string(), 3000.0, 0.0);
+        let manifold_dial = Dial::new("MAP".to_string(), "inHg".to_string(), 35.0, 10.0);
+        let fuel_flow_gauge = Gauge::new("fuel flow".to_string(), "l/h".to_string(), 70.0, 0.0);
+        let mut aircraft = aircraft::Aircraft::from_definition(&definition).airborne(state::State::flying_high());
+        let trimmed = trim::solve(&mut aircraft, &cruise());
+        let previous_state = aircraft.state;
         App{
             camera: camera::Camera::new(),
-            aircraft: aircraft::Aircraft::flying_high(),
-            logger: file,
+            aircraft,
+            definition,
+            clock: clock::Clock::new(),
+            previous_state,
+            logger,
+            outcome: None,
+            wind: calm_layers(),
+            trim_target: cruise(),
+            trimmed,
+            modes: None,
+            derivatives: true,
             velocity_dial,
             altitude_dial,
             climb_rate_dial,
             throttle_gauge,
+            rpm_dial,
+            manifold_dial,
+            fuel_flow_gauge,
+            mixture_gauge,
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,236 @@
+use crate::aircraft::Aircraft;
+use crate::common_math::{deg_to_rad, rad_to_deg, Angles, Quaternion, Vec3};
+use crate::eigen::{self, Complex};
+use crate::trim::{self, Target, Trim};
+
+const NUDGE: f64 = 1e-5; // how far each state gets moved either side for the jacobian
+const LONGITUDINAL: [usize; 4] = [0, 2, 4, 7]; // u, w, q and pitch, the rest are sideways
+
+/*
+what a 172 should do at 50 m/s and 1000 m with 1156 kg on board, worked out from the cruise stability
+derivatives usually quoted from roskam (airplane flight dynamics and automatic flight controls, part I)
+with the mode approximations in nelson (flight stability and automatic control, chapters 4 and 5):
+  short period  wn = sqrt(Za Mq / u - Ma), zeta = -(Mq + Za / u) / 2 wn
+  phugoid       wn = sqrt(-Zu g / u), zeta = -Xu / 2 wn, with the prop losing 37 N of thrust per m/s at 2200 rpm
+  dutch roll    wn = sqrt((Yb Nr + u Nb) / u), zeta = -(Yb + u Nr) / 2 wn u
+  roll          tau = -1 / Lp
+the short period and roll approximations are good to a few percent, the phugoid and dutch roll ones leave
+out coupling that moves them by up to about a fifth, so those get more room. each is the number, how far
+off it can be as a fraction, then the damping and how far off that can be. the roll is a time constant
+ */
+const REFERENCE: [(&str, f64, f64, f64, f64); 4] = [
+    ("short period", 4.81, 0.1, 0.56, 0.05),
+    ("phugoid", 0.277, 0.25, 0.12, 0.06),
+    ("dutch roll", 2.48, 0.25, 0.23, 0.05),
+    ("roll", 0.101, 0.1, 1.0, 0.0),
+];
+
+// one of the ways the plane likes to move on its own once its disturbed
+#[derive(Clone, Copy, Debug)]
+pub struct Mode {
+    pub name: &'static str,
+    pub natural_frequency: f64, // rad/s
+    pub damping: f64, // ratio, 1 for a plain decay and negative if it grows
+    pub period: Option<f64>, // seconds, None if it doesnt go back and forth
+    pub time_constant: f64, // seconds, negative if it grows
+}
+
+impl Mode {
+    fn new(name: &'static str, eigenvalue: Complex) -> Mode {
+        let natural_frequency = eigenvalue.magnitude();
+        Mode {
+            name,
+            natural_frequency,
+            damping: if natural_frequency > 0.0 { -eigenvalue.re / natural_frequency } else { 1.0 },
+            period: if eigenvalue.im != 0.0 { Some(2.0 * std::f64::consts::PI / eigenvalue.im.abs()) } else { None },
+            time_constant: -1.0 / eigenvalue.re,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        let settling = if self.time_constant >= 0.0 {
+            format!("time constant {:.2}s", self.time_constant)
+        } else {
+            format!("doubles every {:.1}s", -self.time_constant * 2.0_f64.ln())
+        };
+        match self.period {
+            Some(period) => format!("{}: {:.2} rad/s, damping {:.2}, period {:.1}s, {}", self.name, self.natural_frequency, self.damping, period, settling),
+            None => format!("{}: {}", self.name, settling),
+        }
+    }
+
+    pub fn fits(&self) -> Option<bool> {
+        // whether its within the reference numbers, None if theres nothing to go on for this one
+        let (_, value, spread, damping, damping_spread) = REFERENCE.iter().find(|(name, ..)| *name == self.name)?;
+        let size = if self.period.is_none() { self.time_constant } else { self.natural_frequency };
+        Some((size - value).abs() <= spread * value && (self.damping - damping).abs() <= *damping_spread)
+    }
+
+    pub fn reference(&self) -> Option<String> {
+        // how it lines up against the reference numbers
+        let (_, value, spread, damping, damping_spread) = REFERENCE.iter().find(|(name, ..)| *name == self.name)?;
+        let off = if self.fits()? { "" } else { ", this is off" };
+        if self.period.is_none() {
+            return Some(format!("172 is {:.3}s \u{b1}{:.0}%{}", value, spread * 100.0, off));
+        }
+        Some(format!("172 is {:.2} rad/s \u{b1}{:.0}%, damping {:.2} \u{b1}{:.2}{}", value, spread * 100.0, damping, damping_spread, off))
+    }
+}
+
+pub fn analyse(aircraft: &mut Aircraft, target: &Target) -> Result<(Trim, Vec<Mode>), String> {
+    /*
+    trims the plane for the target and then linearises it there, dx/dt = A x, with a jacobian of the
+    state derivative. the states are the body air velocity, the body rates and the pitch and bank.
+    the heading doesnt change anything so its left out, and so is the height, the air hardly
+    changes over the few metres these move it. the engine is held at the trimmed rpm.
+    each eigenvalue of A is a mode, and its eigenvector says which way it moves the plane.
+    the trim comes back too so nobody has to solve it again
+     */
+    let trim = trim::solve(aircraft, target)?;
+    let trimmed = aircraft.state;
+    let angles = trimmed.pointing_global();
+    let mut point = [0.0; 8];
+    let body = trimmed.body_air_velocity();
+    let rate = trimmed.angular_rate;
+    point[..6].copy_from_slice(&[body.x, body.y, body.z, rate.x, rate.y, rate.z]);
+    point[6] = deg_to_rad(angles.roll);
+    point[7] = deg_to_rad(angles.altitude);
+
+    let mut matrix = vec![vec![0.0; 8]; 8];
+    for column in 0..8 {
+        let (mut up, mut down) = (point, point);
+        up[column] += NUDGE;
+        down[column] -= NUDGE;
+        let (up, down) = (rates(aircraft, angles.azimouth, &up), rates(aircraft, angles.azimouth, &down));
+        for (row, value) in matrix.iter_mut().enumerate() {
+            value[column] = (up[row] - down[row]) / (2.0 * NUDGE);
+        }
+    }
+    aircraft.state = trimmed;
+
+    let values = eigen::eigenvalues(&matrix).ok_or("the eigenvalues wouldnt settle")?;
+    Ok((trim, name_modes(&matrix, &values, target.airspeed)))
+}
+
+fn rates(aircraft: &mut Aircraft, heading: f64, states: &[f64; 8]) -> [f64; 8] {
+    // puts the plane at these states and sees how fast each one changes
+    let [u, v, w, p, q, r, roll, pitch] = *states;
+    let attitude = Quaternion::from_angles(&Angles::new(heading, rad_to_deg(pitch), rad_to_deg(roll)));
+    let body = Vec3::new(u, v, w);
+    let rate = Vec3::new(p, q, r);
+    let state = &mut aircraft.state;
+    state.attitude = attitude;
+    state.velocity = attitude.rotate(&body) + &state.wind;
+    state.angular_rate = rate;
+    let derivative = aircraft.derivative(&aircraft.state);
+
+    // the body velocity changes with the world acceleration and with the body turning under it
+    let accelerating = attitude.rotate_inverse(&derivative.acceleration) - &rate.cross_product(&body);
+    let turning = derivative.angular_acceleration;
+    // the euler angles dont have a tidy derivative in this convention, so step the attitude a touch and look
+    let ahead = (attitude + &(derivative.attitude * NUDGE)).normalised().as_angles();
+    let behind = (attitude + &(derivative.attitude * -NUDGE)).normalised().as_angles();
+    let roll_rate = deg_to_rad(ahead.roll - behind.roll) / (2.0 * NUDGE);
+    let pitch_rate = deg_to_rad(ahead.altitude - behind.altitude) / (2.0 * NUDGE);
+    [accelerating.x, accelerating.y, accelerating.z, turning.x, turning.y, turning.z, roll_rate, pitch_rate]
+}
+
+fn name_modes(matrix: &[Vec<f64>], values: &[Complex], airspeed: f64) -> Vec<Mode> {
+    /*
+    sorts the eigenvalues into the usual modes by whether their eigenvector is mostly pitching or mostly
+    rolling and yawing. the velocities get divided by the airspeed so theyre angles like the rest.
+    the fast pitching pair is the short period and the slow one the phugoid, the sideways pair is the
+    dutch roll, and the sideways real ones are the roll (fast) and the spiral (slow)
+     */
+    let mut pitching_pairs = vec![];
+    let mut pitching_real = vec![];
+    let mut sideways_pairs = vec![];
+    let mut sideways_real = vec![];
+    for value in values {
+        if value.im < 0.0 {
+            continue; // the other half of a pair
+        }
+        let vector = eigen::eigenvector(matrix, *value);
+        let weight = |index: usize| {
+            let size = vector[index].magnitude() / if index < 3 { airspeed } else { 1.0 };
+            size * size
+        };
+        let pitching: f64 = LONGITUDINAL.iter().map(|index| weight(*index)).sum();
+        let sideways: f64 = (0..8).filter(|index| !LONGITUDINAL.contains(index)).map(weight).sum();
+        match (pitching > sideways, value.im > 0.0) {
+            (true, true) => pitching_pairs.push(*value),
+            (true, false) => pitching_real.push(*value),
+            (false, true) => sideways_pairs.push(*value),
+            (false, false) => sideways_real.push(*value),
+        }
+    }
+    pitching_pairs.sort_by(|a, b| b.magnitude().total_cmp(&a.magnitude()));
+    sideways_pairs.sort_by(|a, b| b.magnitude().total_cmp(&a.magnitude()));
+    sideways_real.sort_by(|a, b| b.re.abs().total_cmp(&a.re.abs()));
+
+    let mut modes = vec![];
+    if pitching_pairs.len() >= 2 {
+        modes.push(Mode::new("short period", pitching_pairs.remove(0)));
+        modes.push(Mode::new("phugoid", pitching_pairs.pop().unwrap()));
+    } else if let Some(value) = pitching_pairs.pop() {
+        // an overdamped short period splits into two real ones, so a lone pair is the phugoid
+        modes.push(Mode::new("phugoid", value));
+    }
+    if !sideways_pairs.is_empty() {
+        modes.push(Mode::new("dutch roll", sideways_pairs.remove(0)));
+    }
+    if sideways_real.len() >= 2 {
+        modes.push(Mode::new("roll", sideways_real.remove(0)));
+        modes.push(Mode::new("spiral", sideways_real.pop().unwrap()));
+    }
+    // anything that didnt fit the usual picture still gets shown
+    for value in pitching_pairs.into_iter().chain(pitching_real).chain(sideways_pairs).chain(sideways_real) {
+        modes.push(Mode::new("other", value));
+    }
+    modes
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::definition::AircraftDefinition;
+
+    fn find(modes: &[Mode], name: &str) -> Mode {
+        *modes.iter().find(|mode| mode.name == name).unwrap_or_else(|| panic!("no {} in {:?}", name, modes))
+    }
+
+    #[test]
+    fn finds_the_usual_modes() {
+        let mut plane = Aircraft::flying_high();
+        let (_, modes) = analyse(&mut plane, &Target::new(50.0, 0.0, 0.0)).unwrap();
+        for name in ["short period", "phugoid", "dutch roll", "roll"] {
+            let mode = find(&modes, name);
+            assert!(mode.fits().unwrap(), "{} {:?}", name, mode.reference());
+        }
+        // the short period is a lot quicker than the phugoid, and the spiral is slow whichever way it goes
+        assert!(find(&modes, "short period").natural_frequency > 5.0 * find(&modes, "phugoid").natural_frequency);
+        assert!(find(&modes, "spiral").time_constant.abs() > 10.0);
+    }
+
+    #[test]
+    fn the_wing_and_fin_fit_too() {
+        let mut plane = Aircraft::flying_high();
+        plane.aero_model = AircraftDefinition::cessna().aero_model(false);
+        let (_, modes) = analyse(&mut plane, &Target::new(50.0, 0.0, 0.0)).unwrap();
+        for name in ["short period", "phugoid", "dutch roll", "roll"] {
+            assert!(find(&modes, name).fits().unwrap(), "{} {:?}", name, find(&modes, name).reference());
+        }
+        assert!(find(&modes, "spiral").time_constant.abs() > 10.0);
+    }
+
+    #[test]
+    fn leaves_it_trimmed() {
+        let mut plane = Aircraft::flying_high();
+        let (trim, _) = analyse(&mut plane, &Target::new(50.0, 0.0, 0.0)).unwrap();
+        // the trim it hands back is the one the plane is sat at
+        assert!((plane.state.pointing_global().altitude - trim.pitch).abs() < 1e-9);
+        let derivative = plane.derivative(&plane.state);
+        assert!(derivative.acceleration.magnitude() < 1e-6);
+        assert!(derivative.angular_acceleration.magnitude() < 1e-6);
+    }
+}
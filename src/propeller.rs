@@ -0,0 +1,183 @@
+use serde::Deserialize;
+
+use crate::common_math::{interpolate, Vec3};
+use crate::definition::AircraftDefinition;
+
+const MAX_REVS: f64 = 60.0; // rev/s, way past redline, just the top of the search
+const P_FACTOR: f64 = 0.4; // how far the thrust moves out across the disc per radian, as a fraction of the radius
+const SWIRL_RADIUS: f64 = 0.7; // fraction of the radius where the slipstream carries its spin
+const WASH_AT_FIN: f64 = 0.5; // how much of the slipstream is left by the time it gets to the fin, and how much of the fin is in it
+
+// the left turning tendencies, for a prop that turns clockwise seen from the cockpit like most american engines.
+// theyre all on normally, turning them off one at a time shows what each one does
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Effects {
+    pub torque: bool, // the engine twisting the plane the other way, rolls it left
+    pub p_factor: bool, // the down going blade biting harder at high alpha, yaws it left
+    pub slipstream: bool, // the spiralling wash hitting the left of the fin, yaws it left
+    pub gyroscopic: bool, // the prop precessing when the nose moves, pitching up yaws it right
+}
+
+impl Effects {
+    pub fn all() -> Effects {
+        Effects { torque: true, p_factor: true, slipstream: true, gyroscopic: true }
+    }
+
+    #[allow(dead_code)]
+    pub fn none() -> Effects {
+        Effects { torque: false, p_factor: false, slipstream: false, gyroscopic: false }
+    }
+}
+
+// thrust and power coefficients against advance ratio J = V/nD, a fixed pitch prop windmills and makes drag past the
+// point where the thrust goes negative
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Propeller {
+    pub diameter: f64, // m
+    pub thrust_coefficient: Vec<(f64, f64)>,
+    pub power_coefficient: Vec<(f64, f64)>,
+}
+
+impl Propeller {
+    #[allow(dead_code)]
+    pub fn cessna() -> Propeller {
+        AircraftDefinition::cessna().propeller
+    }
+
+    pub fn advance_ratio(&self, airspeed: f64, rpm: f64) -> f64 {
+        let revs = rpm / 60.0;
+        if revs <= 0.0 {
+            return self.thrust_coefficient[self.thrust_coefficient.len() - 1].0;
+        }
+        airspeed.max(0.0) / (revs * self.diameter)
+    }
+
+    pub fn thrust(&self, airspeed: f64, rpm: f64, density: f64) -> f64 {
+        // T = CT rho n^2 D^4, fine at zero airspeed because J is just 0
+        let revs = rpm / 60.0;
+        interpolate(&self.thrust_coefficient, self.advance_ratio(airspeed, rpm)) * density * revs.powf(2.0) * self.diameter.powf(4.0)
+    }
+
+    pub fn power_required(&self, airspeed: f64, rpm: f64, density: f64) -> f64 {
+        // P = CP rho n^3 D^5, this is what it takes to turn the prop at this rpm
+        let revs = rpm / 60.0;
+        interpolate(&self.power_coefficient, self.advance_ratio(airspeed, rpm)) * density * revs.powf(3.0) * self.diameter.powf(5.0)
+    }
+
+    pub fn torque(&self, airspeed: f64, rpm: f64, density: f64) -> f64 {
+        // N m the air takes to turn the prop, the engine pushes back on the airframe just as hard
+        let omega = rpm * 2.0 * std::f64::consts::PI / 60.0;
+        if omega < 1.0 {
+            return 0.0;
+        }
+        self.power_required(airspeed, rpm, density) / omega
+    }
+
+    pub fn thrust_offset(&self, alpha: f64, sideslip: f64) -> Vec3 {
+        /*
+        p factor, where on the disc the thrust acts. with air coming up from underneath the blade going down
+        on the right meets it head on and pulls harder, with air from the right its the one going across the top
+         */
+        let radius = self.diameter / 2.0;
+        Vec3::new(0.0, -P_FACTOR * radius * alpha.sin(), P_FACTOR * radius * sideslip.sin())
+    }
+
+    pub fn slipstream(&self, airspeed: f64, thrust: f64, torque: f64, density: f64) -> Vec3 {
+        /*
+        how much faster the fin goes through the air because of the prop wash, body coords.
+        the wash speeds up by momentum theory and carries the torque off as spin, clockwise from behind
+        so over the top it goes to the right, and the fin moves left through it
+         */
+        let disc = std::f64::consts::PI * (self.diameter / 2.0).powf(2.0);
+        let airspeed = airspeed.max(0.0);
+        let wash = (airspeed.powf(2.0) + 2.0 * thrust.max(0.0) / (density * disc)).sqrt();
+        if wash < 1.0 {
+            return Vec3::new(0.0, 0.0, 0.0);
+        }
+        let swirl = torque.max(0.0) / (density * disc * wash * SWIRL_RADIUS * self.diameter / 2.0);
+        Vec3::new(wash - airspeed, swirl, 0.0) * WASH_AT_FIN
+    }
+
+    #[allow(dead_code)]
+    pub fn efficiency(&self, airspeed: f64, rpm: f64) -> f64 {
+        // eta = J CT / CP, how much of the shaft power ends up pushing the plane
+        let advance_ratio = self.advance_ratio(airspeed, rpm);
+        let power_coefficient = interpolate(&self.power_coefficient, advance_ratio);
+        if power_coefficient <= 0.0 {
+            return 0.0;
+        }
+        (advance_ratio * interpolate(&self.thrust_coefficient, advance_ratio) / power_coefficient).max(0.0)
+    }
+
+    #[allow(dead_code)]
+    pub fn rpm_for_power(&self, power: f64, airspeed: f64, density: f64) -> f64 {
+        /*
+        a fixed pitch prop speeds up until it soaks up all the power its given
+        power required only goes up with rpm, so just bisect for it
+         */
+        let mut low = 0.0;
+        let mut high = MAX_REVS * 60.0;
+        for _ in 0..60 {
+            let middle = (low + high) / 2.0;
+            if self.power_required(airspeed, middle, density) < power {
+                low = middle;
+            } else {
+                high = middle;
+            }
+        }
+        (low + high) / 2.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn static_thrust_is_finite() {
+        let propeller = Propeller::cessna();
+        let rpm = propeller.rpm_for_power(120e3, 0.0, 1.225);
+        let thrust = propeller.thrust(0.0, rpm, 1.225);
+        assert!(rpm > 2000.0 && rpm < 3000.0);
+        assert!(thrust > 2000.0 && thrust < 4000.0);
+    }
+
+    #[test]
+    fn thrust_falls_with_airspeed() {
+        let propeller = Propeller::cessna();
+        let mut last_thrust = f64::INFINITY;
+        for airspeed in [0.0, 10.0, 20.0, 30.0, 40.0, 50.0, 60.0] {
+            let rpm = propeller.rpm_for_power(100e3, airspeed, 1.225);
+            let thrust = propeller.thrust(airspeed, rpm, 1.225);
+            assert!(thrust < last_thrust);
+            // and never better than the power going in
+            assert!(thrust * airspeed < 100e3);
+            last_thrust = thrust;
+        }
+    }
+
+    #[test]
+    fn turns_left() {
+        let propeller = Propeller::cessna();
+        // nose up the thrust moves right, which is -y for us
+        assert!(propeller.thrust_offset(0.2, 0.0).y < 0.0);
+        assert_eq!(propeller.thrust_offset(0.0, 0.0).magnitude(), 0.0);
+        // full power on the ground theres a good strong wash with a spin in it
+        let torque = propeller.torque(0.0, 2400.0, 1.225);
+        assert!(torque > 300.0 && torque < 700.0);
+        let wash = propeller.slipstream(0.0, propeller.thrust(0.0, 2400.0, 1.225), torque, 1.225);
+        assert!(wash.x > 10.0 && wash.y > 1.0);
+        // and it mostly goes away gliding with the engine at idle
+        let idle = propeller.slipstream(40.0, propeller.thrust(40.0, 700.0, 1.225), propeller.torque(40.0, 700.0, 1.225), 1.225);
+        assert!(idle.magnitude() < 0.1 * wash.magnitude());
+    }
+
+    #[test]
+    fn efficiency_peaks_in_cruise() {
+        let propeller = Propeller::cessna();
+        assert_eq!(propeller.efficiency(0.0, 2400.0), 0.0);
+        let cruise = propeller.efficiency(55.0, 2400.0);
+        assert!(cruise > 0.7 && cruise < 0.9);
+    }
+}
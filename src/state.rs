@@ -1,55 +1,142 @@
-use crate::common_math;
-
-pub struct State {
-    pub position: common_math::Vec3,
-    pub pointing_global: common_math::Angles,
-    pub angular_rate: common_math::Angles,
-    pub velocity: common_math::Vec3,
-    pub acceleration: common_math::Vec3
-}
-
-impl State {
-    #[allow(dead_code)]
-    pub fn new() -> State {
-        State {
-            pointing_global: common_math::Angles::new(0.0,0.0,0.0),
-            angular_rate: common_math::Angles::new(0.0,0.0,0.0),
-            position: common_math::Vec3::new(0.0,0.0,0.0),
-            velocity: common_math::Vec3::new(0.0,0.0,0.0),
-            acceleration: common_math::Vec3::new(0.0,0.0,0.0),
-        }
-    }
-
-    pub fn flying() -> State {
-        State {
-            pointing_global: common_math::Angles::new(0.0,6.0,0.0),
-            angular_rate: common_math::Angles::new(0.0,0.0,0.0),
-            position: common_math::Vec3::new(0.0,0.0,100.0),
-            velocity: common_math::Vec3::new(40.0,0.0,0.0),
-            acceleration: common_math::Vec3::new(0.0,0.0,0.0),
-        }
-    }
-
-    pub fn flying_high() -> State {
-        State {
-            pointing_global: common_math::Angles::new(0.0,6.0,0.0),
-            angular_rate: common_math::Angles::new(0.0,0.0,0.0),
-            position: common_math::Vec3::new(0.0,0.0,1000.0),
-            velocity: common_math::Vec3::new(40.0,0.0,0.0),
-            acceleration: common_math::Vec3::new(0.0,0.0,0.0),
-        }
-    }
-    
-    pub fn runway() -> State {
-        State {
-            pointing_global: common_math::Angles::new(0.0,0.0,0.0),
-            angular_rate: common_math::Angles::new(0.0,0.0,0.0),
-            position: common_math::Vec3::new(0.0,0.0,0.0),
-            velocity: common_math::Vec3::new(0.0,0.0,0.0),
-            acceleration: common_math::Vec3::new(0.0,0.0,0.0),
-        }
-    }
-    pub fn log(&self) -> String {
-        format!("{{\"position\": {}, \"pointing_global\": {}, \"angular_rate\": {}, \"velocity\": {}, \"acceleration\": {}}}", self.position.jsonify(), self.pointing_global.jsonify(), self.angular_rate.jsonify(), self.velocity.jsonify(), self.acceleration.jsonify())
-    }
+use crate::common_math;
+
+#[derive(Clone, Copy)]
+pub struct State {
+    pub position: common_math::Vec3,
+    pub attitude: common_math::Quaternion, // rotates body coords into world coords
+    pub angular_rate: common_math::Vec3, // body axis rates (roll, pitch, yaw) in rad/s
+    pub velocity: common_math::Vec3, // over the ground
+    pub acceleration: common_math::Vec3,
+    pub wind: common_math::Vec3, // what the air is doing where the plane is, world coords
+    pub gust_rate: common_math::Vec3, // body rates the turbulence twists the air round the plane with
+}
+
+impl State {
+    #[allow(dead_code)]
+    pub fn new() -> State {
+        State {
+            attitude: common_math::Quaternion::identity(),
+            angular_rate: common_math::Vec3::new(0.0,0.0,0.0),
+            position: common_math::Vec3::new(0.0,0.0,0.0),
+            velocity: common_math::Vec3::new(0.0,0.0,0.0),
+            acceleration: common_math::Vec3::new(0.0,0.0,0.0),
+            wind: common_math::Vec3::new(0.0,0.0,0.0),
+            gust_rate: common_math::Vec3::new(0.0,0.0,0.0),
+        }
+    }
+
+    pub fn flying() -> State {
+        State {
+            attitude: common_math::Quaternion::from_angles(&common_math::Angles::new(0.0,6.0,0.0)),
+            angular_rate: common_math::Vec3::new(0.0,0.0,0.0),
+            position: common_math::Vec3::new(0.0,0.0,100.0),
+            velocity: common_math::Vec3::new(40.0,0.0,0.0),
+            acceleration: common_math::Vec3::new(0.0,0.0,0.0),
+            wind: common_math::Vec3::new(0.0,0.0,0.0),
+            gust_rate: common_math::Vec3::new(0.0,0.0,0.0),
+        }
+    }
+
+    pub fn flying_high() -> State {
+        State {
+            attitude: common_math::Quaternion::from_angles(&common_math::Angles::new(0.0,6.0,0.0)),
+            angular_rate: common_math::Vec3::new(0.0,0.0,0.0),
+            position: common_math::Vec3::new(0.0,0.0,1000.0),
+            velocity: common_math::Vec3::new(40.0,0.0,0.0),
+            acceleration: common_math::Vec3::new(0.0,0.0,0.0),
+            wind: common_math::Vec3::new(0.0,0.0,0.0),
+            gust_rate: common_math::Vec3::new(0.0,0.0,0.0),
+        }
+    }
+    
+    pub fn runway() -> State {
+        State {
+            attitude: common_math::Quaternion::identity(),
+            angular_rate: common_math::Vec3::new(0.0,0.0,0.0),
+            position: common_math::Vec3::new(0.0,0.0,0.92), // the CG sits about a metre up with the gear squashed a bit
+            velocity: common_math::Vec3::new(0.0,0.0,0.0),
+            acceleration: common_math::Vec3::new(0.0,0.0,0.0),
+            wind: common_math::Vec3::new(0.0,0.0,0.0),
+            gust_rate: common_math::Vec3::new(0.0,0.0,0.0),
+        }
+    }
+
+    pub fn air_velocity(&self) -> common_math::Vec3 {
+        // how the plane is moving through the air rather than over the ground, in world coords
+        self.velocity - &self.wind
+    }
+
+    pub fn body_air_velocity(&self) -> common_math::Vec3 {
+        self.attitude.rotate_inverse(&self.air_velocity())
+    }
+
+    pub fn air_rate(&self) -> common_math::Vec3 {
+        // how fast the plane is turning relative to the air around it, which is what the aero damping feels
+        self.angular_rate - &self.gust_rate
+    }
+
+    pub fn airspeed(&self) -> f64 {
+        self.air_velocity().magnitude()
+    }
+
+    pub fn groundspeed(&self) -> f64 {
+        common_math::Vec3::new(self.velocity.x, self.velocity.y, 0.0).magnitude()
+    }
+
+    #[allow(dead_code)]
+    pub fn track(&self) -> f64 {
+        // compass degrees the plane is going over the ground, x is north and y is west
+        (-self.velocity.y).atan2(self.velocity.x).to_degrees().rem_euclid(360.0)
+    }
+
+    pub fn alpha(&self) -> f64 {
+        // the body z axis points up, so air coming up from underneath is a negative z velocity
+        let body_velocity = self.body_air_velocity();
+        if body_velocity.magnitude() == 0.0 {
+            return 0.0;
+        }
+        (-body_velocity.z).atan2(body_velocity.x)
+    }
+
+    pub fn sideslip(&self) -> f64 {
+        // positive when the air is coming from the right, and body y points left
+        let body_velocity = self.body_air_velocity();
+        let airspeed = body_velocity.magnitude();
+        if airspeed == 0.0 {
+            return 0.0;
+        }
+        (-body_velocity.y / airspeed).asin()
+    }
+
+    pub fn interpolate(&self, next: &State, fraction: f64) -> State {
+        // somewhere between two physics steps, only for drawing
+        let blend = |from: &common_math::Vec3, to: &common_math::Vec3| *from * (1.0 - fraction) + &(*to * fraction);
+        State {
+            position: blend(&self.position, &next.position),
+            attitude: self.attitude.slerp(&next.attitude, fraction),
+            angular_rate: blend(&self.angular_rate, &next.angular_rate),
+            velocity: blend(&self.velocity, &next.velocity),
+            acceleration: blend(&self.acceleration, &next.acceleration),
+            wind: blend(&self.wind, &next.wind),
+            gust_rate: blend(&self.gust_rate, &next.gust_rate),
+        }
+    }
+
+    pub fn pointing_global(&self) -> common_math::Angles {
+        self.attitude.as_angles()
+    }
+
+    #[allow(dead_code)]
+    pub fn log(&self) -> String {
+        self.log_with(&[])
+    }
+
+    pub fn log_with(&self, extras: &[(&str, String)]) -> String {
+        // extras are other things from the aircraft that want to end up in the same line of the log
+        let mut extra = String::new();
+        for (name, value) in extras {
+            extra.push_str(&format!(", \"{}\": {}", name, value));
+        }
+        format!("{{\"position\": {}, \"pointing_global\": {}, \"attitude\": {}, \"angular_rate\": {}, \"velocity\": {}, \"acceleration\": {}, \"wind\": {}, \"airspeed\": {}, \"groundspeed\": {}{}}}", self.position.jsonify(), self.pointing_global().jsonify(), self.attitude.jsonify(), self.angular_rate.jsonify(), self.velocity.jsonify(), self.acceleration.jsonify(), self.wind.jsonify(), self.airspeed(), self.groundspeed(), extra)
+    }
 }
\ No newline at end of file
@@ -0,0 +1,224 @@
+use serde::Deserialize;
+
+use crate::common_math::Vec3;
+use crate::state::State;
+
+// any faster than this and the gear legs fold up
+const MAX_SINK_RATE: f64 = 5.0; // m/s
+// how long after the wheels touch to keep watching for the biggest G
+const SETTLE_TIME: f64 = 0.5; // s
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CrashCause {
+    HardLanding,
+    WingStrike,
+    TailStrike,
+    PropStrike,
+    BellyLanding,
+}
+
+// a bit of the plane that isnt meant to touch the ground, and what it means if it does
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Contact {
+    pub cause: CrashCause,
+    pub position: Vec3, // body coords from the reference point
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct TouchdownReport {
+    pub sink_rate: f64, // m/s, positive going down
+    pub g_load: f64, // the most G seen while the wheels were settling
+    pub bank: f64, // degrees
+    pub pitch: f64, // degrees
+    pub groundspeed: f64, // m/s
+    pub position: Vec3,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct CrashEvent {
+    pub cause: CrashCause,
+    pub report: TouchdownReport,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Outcome {
+    Landed(TouchdownReport),
+    Crashed(CrashEvent),
+}
+
+pub struct Monitor {
+    settling: Option<(TouchdownReport, f64)>, // the report so far and how long its got left
+}
+
+impl CrashCause {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            CrashCause::HardLanding => "came down too hard and folded the gear",
+            CrashCause::WingStrike => "dragged a wingtip",
+            CrashCause::TailStrike => "scraped the tail",
+            CrashCause::PropStrike => "put the prop into the ground",
+            CrashCause::BellyLanding => "landed on the belly",
+        }
+    }
+}
+
+impl TouchdownReport {
+    pub fn from_state(state: &State, sink_rate: f64) -> TouchdownReport {
+        let angles = state.pointing_global();
+        TouchdownReport {
+            sink_rate,
+            g_load: load_factor(state),
+            bank: angles.roll,
+            pitch: angles.altitude,
+            groundspeed: Vec3::new(state.velocity.x, state.velocity.y, 0.0).magnitude(),
+            position: state.position,
+        }
+    }
+
+    pub fn rating(&self) -> &'static str {
+        if self.sink_rate < 1.0 {
+            "greaser"
+        } else if self.sink_rate < 2.5 {
+            "firm"
+        } else {
+            "hard, get the gear looked at"
+        }
+    }
+
+    pub fn jsonify(&self) -> String {
+        format!("{{\"sink_rate\": {}, \"g_load\": {}, \"bank\": {}, \"pitch\": {}, \"groundspeed\": {}, \"position\": {}}}", self.sink_rate, self.g_load, self.bank, self.pitch, self.groundspeed, self.position.jsonify())
+    }
+}
+
+impl Outcome {
+    pub fn jsonify(&self) -> String {
+        match self {
+            Outcome::Landed(report) => format!("{{\"landed\": {}}}", report.jsonify()),
+            Outcome::Crashed(crash) => format!("{{\"crashed\": \"{:?}\", \"report\": {}}}", crash.cause, crash.report.jsonify()),
+        }
+    }
+}
+
+impl Monitor {
+    pub fn new() -> Monitor {
+        Monitor {
+            settling: None,
+        }
+    }
+
+    pub fn check(&mut self, contacts: &[Contact], previous: &State, next: &State, was_on_ground: bool, on_ground: bool, dt: f64) -> Option<Outcome> {
+        /*
+        call once a step with the states either side of it. any of the planes contacts hitting the ground
+        is a crash straight away, otherwise a landing gets reported once its settled
+         */
+        for Contact { cause, position } in contacts {
+            if (next.position + &next.attitude.rotate(position)).z < 0.0 {
+                let report = match self.settling {
+                    Some((report, _)) => report,
+                    None => TouchdownReport::from_state(next, -previous.velocity.z),
+                };
+                return Some(Outcome::Crashed(CrashEvent { cause: *cause, report }));
+            }
+        }
+
+        if on_ground && !was_on_ground && self.settling.is_none() {
+            let report = TouchdownReport::from_state(next, -previous.velocity.z);
+            if report.sink_rate > MAX_SINK_RATE {
+                return Some(Outcome::Crashed(CrashEvent { cause: CrashCause::HardLanding, report }));
+            }
+            self.settling = Some((report, SETTLE_TIME));
+            return None;
+        }
+
+        if let Some((mut report, time_left)) = self.settling {
+            report.g_load = report.g_load.max(load_factor(next));
+            if time_left - dt <= 0.0 {
+                self.settling = None;
+                return Some(Outcome::Landed(report));
+            }
+            self.settling = Some((report, time_left - dt));
+        }
+        None
+    }
+}
+
+fn load_factor(state: &State) -> f64 {
+    // what the pilot feels through the seat, 1 sitting still on the ground
+    let felt = state.acceleration + &Vec3::new(0.0, 0.0, 9.81);
+    state.attitude.rotate_inverse(&felt).z / 9.81
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::common_math::{Angles, Quaternion};
+    use crate::definition::AircraftDefinition;
+
+    fn contacts() -> Vec<Contact> {
+        AircraftDefinition::cessna().geometry.contacts
+    }
+
+    fn arriving(sink_rate: f64, bank: f64) -> (State, State) {
+        let mut previous = State::runway();
+        previous.position.z = 1.0;
+        previous.velocity = Vec3::new(30.0, 0.0, -sink_rate);
+        previous.attitude = Quaternion::from_angles(&Angles::new(0.0, 3.0, bank));
+        let mut next = previous;
+        next.position.z = 0.95;
+        (previous, next)
+    }
+
+    #[test]
+    fn reports_a_landing_once_its_settled() {
+        let mut monitor = Monitor::new();
+        let (previous, next) = arriving(1.5, 0.0);
+        assert!(monitor.check(&contacts(), &previous, &next, false, true, 0.1).is_none());
+        let mut outcome = None;
+        for _ in 0..10 {
+            outcome = outcome.or(monitor.check(&contacts(), &next, &next, true, true, 0.1));
+        }
+        match outcome {
+            Some(Outcome::Landed(report)) => {
+                assert!((report.sink_rate - 1.5).abs() < 1e-9);
+                assert!((report.pitch - 3.0).abs() < 1e-9);
+                assert!((report.groundspeed - 30.0).abs() < 1e-9);
+                assert_eq!(report.rating(), "firm");
+            },
+            _ => panic!("should have landed"),
+        }
+    }
+
+    #[test]
+    fn crashes() {
+        let mut monitor = Monitor::new();
+        let (previous, next) = arriving(7.0, 0.0);
+        match monitor.check(&contacts(), &previous, &next, false, true, 0.01) {
+            Some(Outcome::Crashed(crash)) => assert_eq!(crash.cause, CrashCause::HardLanding),
+            _ => panic!("should have crashed"),
+        }
+
+        let mut monitor = Monitor::new();
+        let (previous, next) = arriving(1.0, 60.0);
+        match monitor.check(&contacts(), &previous, &next, false, true, 0.01) {
+            Some(Outcome::Crashed(crash)) => assert_eq!(crash.cause, CrashCause::WingStrike),
+            _ => panic!("should have crashed"),
+        }
+    }
+
+    #[test]
+    fn goes_on_the_planes_own_shape() {
+        // a 10 degree bank on the 172 keeps the tips off, a glider sized wing digs one in
+        let (previous, next) = arriving(1.0, 10.0);
+        assert!(Monitor::new().check(&contacts(), &previous, &next, false, true, 0.01).is_none());
+        let long_wings: Vec<Contact> = contacts().iter().map(|contact| match contact.cause {
+            CrashCause::WingStrike => Contact { position: Vec3::new(contact.position.x, contact.position.y * 2.2, contact.position.z), ..*contact },
+            _ => *contact,
+        }).collect();
+        match Monitor::new().check(&long_wings, &previous, &next, false, true, 0.01) {
+            Some(Outcome::Crashed(crash)) => assert_eq!(crash.cause, CrashCause::WingStrike),
+            _ => panic!("should have caught a tip"),
+        }
+    }
+}
@@ -0,0 +1,256 @@
+use crate::aircraft::Aircraft;
+use crate::common_math::{deg_to_rad, rad_to_deg, Angles, Quaternion, Vec3};
+use crate::controls::ControlSurfaces;
+
+const TOLERANCE: f64 = 1e-8; // on the accelerations left over, m/s^2 and rad/s^2
+const ITERATIONS: usize = 50;
+const NUDGE: f64 = 1e-6; // how far each unknown gets moved to see what it does
+
+// what the plane should be doing once its trimmed
+#[derive(Clone, Copy, PartialEq)]
+pub struct Target {
+    pub airspeed: f64, // m/s
+    pub climb_rate: f64, // m/s, negative going down
+    pub bank: f64, // degrees, positive is left wing down like the Angles roll, and it turns that way
+}
+
+impl Target {
+    pub fn new(airspeed: f64, climb_rate: f64, bank: f64) -> Target {
+        Target { airspeed, climb_rate, bank }
+    }
+}
+
+// what it took to get there, all angles in degrees
+#[derive(Clone, Copy, Debug)]
+pub struct Trim {
+    pub alpha: f64,
+    pub sideslip: f64,
+    pub pitch: f64,
+    pub elevator: f64,
+    pub aileron: f64,
+    pub rudder: f64,
+    pub throttle: f64,
+    pub rpm: f64,
+}
+
+pub fn solve(aircraft: &mut Aircraft, target: &Target) -> Result<Trim, String> {
+    /*
+    finds the alpha, sideslip, control surfaces and prop rpm that leave nothing accelerating the plane
+    apart from going round the turn, by newtons method on the state derivative. then works out the
+    throttle that holds the engine at that rpm. the plane is only changed if theres an answer,
+    it stays where it was at the same height and heading otherwise
+     */
+    if target.airspeed <= 0.0 || target.climb_rate.abs() >= target.airspeed {
+        return Err("it cant climb or sink faster than its going".to_string());
+    }
+    if target.bank.abs() >= 90.0 {
+        return Err("it cant hold its height on its side".to_string());
+    }
+    let before = (aircraft.state, aircraft.controls.elevator, aircraft.controls.trim, aircraft.controls.aileron, aircraft.controls.rudder, aircraft.engine.rpm, aircraft.wing.stalled);
+    aircraft.wing.stalled = false;
+    let result = search(aircraft, target);
+    if result.is_err() {
+        let (state, elevator, trim, aileron, rudder, rpm, stalled) = before;
+        aircraft.state = state;
+        aircraft.controls.elevator = elevator;
+        aircraft.controls.trim = trim;
+        aircraft.controls.aileron = aileron;
+        aircraft.controls.rudder = rudder;
+        aircraft.engine.rpm = rpm;
+        aircraft.wing.stalled = stalled;
+    }
+    result
+}
+
+fn search(aircraft: &mut Aircraft, target: &Target) -> Result<Trim, String> {
+    // alpha, sideslip, elevator, aileron, rudder and the rpm in thousands so theyre all about the same size
+    let heading = aircraft.state.pointing_global().azimouth;
+    let mut unknowns = [0.1, 0.0, 0.0, 0.0, 0.0, aircraft.engine.rpm.max(1500.0) / 1000.0];
+    let mut converged = false;
+    let mut deepest: f64 = 0.0;
+    for _ in 0..ITERATIONS {
+        deepest = deepest.max(unknowns[0]);
+        let left_over = residuals(aircraft, target, heading, &unknowns);
+        if left_over.iter().all(|residual| residual.abs() < TOLERANCE) {
+            converged = true;
+            break;
+        }
+        let mut jacobian = [[0.0; 6]; 6];
+        for column in 0..6 {
+            let mut nudged = unknowns;
+            nudged[column] += NUDGE;
+            let moved = residuals(aircraft, target, heading, &nudged);
+            for row in 0..6 {
+                jacobian[row][column] = (moved[row] - left_over[row]) / NUDGE;
+            }
+        }
+        let step = match solve_linear(jacobian, left_over.map(|residual| -residual)) {
+            Some(step) => step,
+            None => break,
+        };
+        // dont let it jump too far on one go, the aero isnt straight lines for long
+        let largest = step.iter().fold(0.0_f64, |largest, value| largest.max(value.abs()));
+        let scale = if largest > 0.1 { 0.1 / largest } else { 1.0 };
+        for (unknown, change) in unknowns.iter_mut().zip(step) {
+            *unknown += change * scale;
+        }
+    }
+
+    // usually when it gives up its because the wing cant make enough lift, and its gone looking past the stall
+    let [alpha, sideslip, elevator, aileron, rudder, revs] = unknowns;
+    if aircraft.wing.stall_margin(alpha) < 0.0 || (!converged && aircraft.wing.stall_margin(deepest) < 0.0) {
+        return Err(format!("it would have to fly stalled, at {:.1} degrees of alpha", rad_to_deg(alpha.max(deepest))));
+    }
+    if !converged {
+        return Err("couldnt find a steady way to fly that".to_string());
+    }
+
+    // it found somewhere the sums work, now check the plane can actually get there
+    place(aircraft, target, heading, &unknowns);
+    if let Some(surface) = ControlSurfaces::out_of_travel(elevator, aileron, rudder) {
+        return Err(format!("theres not enough {} to hold it there", surface));
+    }
+    if revs <= 0.0 {
+        return Err("the prop would have to be going backwards".to_string());
+    }
+    let throttle = aircraft.steady_throttle()?;
+    aircraft.throttle_percent = throttle;
+    aircraft.controls.hold_elevator(elevator);
+    Ok(Trim {
+        alpha: rad_to_deg(alpha),
+        sideslip: rad_to_deg(sideslip),
+        pitch: aircraft.state.pointing_global().altitude,
+        elevator: rad_to_deg(elevator),
+        aileron: rad_to_deg(aileron),
+        rudder: rad_to_deg(rudder),
+        throttle,
+        rpm: revs * 1000.0,
+    })
+}
+
+fn place(aircraft: &mut Aircraft, target: &Target, heading: f64, unknowns: &[f64; 6]) -> Vec3 {
+    /*
+    puts the plane where these unknowns say, and hands back the acceleration going round the turn needs.
+    the flight path is pitched up by the climb angle and banked round itself, then the body sits
+    alpha above it and sideslip off to the side
+     */
+    let [alpha, sideslip, elevator, aileron, rudder, revs] = *unknowns;
+    let climb = (target.climb_rate / target.airspeed).asin();
+    let path = Quaternion::from_angles(&Angles::new(heading, rad_to_deg(climb), target.bank));
+    let attitude = path * &Quaternion::from_axis_angle(&Vec3::new(0.0, 0.0, 1.0), sideslip) * &Quaternion::from_axis_angle(&Vec3::new(0.0, 1.0, 0.0), -alpha);
+    let air_velocity = path.rotate(&Vec3::new(target.airspeed, 0.0, 0.0));
+    // a balanced turn at this bank, positive is turning left
+    let turn_rate = 9.81 * deg_to_rad(target.bank).tan() / (target.airspeed * climb.cos());
+    let turning = Vec3::new(0.0, 0.0, turn_rate);
+
+    let state = &mut aircraft.state;
+    state.wind = aircraft.wind.at(state.position.z);
+    state.gust_rate = Vec3::new(0.0, 0.0, 0.0);
+    state.attitude = attitude;
+    state.velocity = air_velocity + &state.wind;
+    state.angular_rate = attitude.rotate_inverse(&turning);
+    state.acceleration = turning.cross_product(&air_velocity);
+    aircraft.controls.elevator = elevator;
+    aircraft.controls.trim = 0.0;
+    aircraft.controls.aileron = aileron;
+    aircraft.controls.rudder = rudder;
+    aircraft.engine.rpm = revs * 1000.0;
+    state.acceleration
+}
+
+fn residuals(aircraft: &mut Aircraft, target: &Target, heading: f64, unknowns: &[f64; 6]) -> [f64; 6] {
+    // whatever acceleration is left over once the turn has had what it needs
+    let wanted = place(aircraft, target, heading, unknowns);
+    let derivative = aircraft.derivative(&aircraft.state);
+    let linear = derivative.acceleration - &wanted;
+    let angular = derivative.angular_acceleration;
+    [linear.x, linear.y, linear.z, angular.x, angular.y, angular.z]
+}
+
+fn solve_linear(mut matrix: [[f64; 6]; 6], mut right: [f64; 6]) -> Option<[f64; 6]> {
+    // gaussian elimination, swapping rows to keep the biggest number on the diagonal
+    for column in 0..6 {
+        let pivot = (column..6).max_by(|a, b| matrix[*a][column].abs().total_cmp(&matrix[*b][column].abs()))?;
+        if matrix[pivot][column].abs() < 1e-12 {
+            return None;
+        }
+        matrix.swap(column, pivot);
+        right.swap(column, pivot);
+        for row in column + 1..6 {
+            let factor = matrix[row][column] / matrix[column][column];
+            let pivot_row = matrix[column];
+            for (value, above) in matrix[row].iter_mut().zip(pivot_row).skip(column) {
+                *value -= factor * above;
+            }
+            right[row] -= factor * right[column];
+        }
+    }
+    let mut answer = [0.0; 6];
+    for row in (0..6).rev() {
+        let known: f64 = (row + 1..6).map(|across| matrix[row][across] * answer[across]).sum();
+        answer[row] = (right[row] - known) / matrix[row][row];
+    }
+    Some(answer)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn fly(plane: &mut Aircraft, seconds: f64) {
+        for _ in 0..(seconds / 0.005) as usize {
+            plane.do_step(0.005);
+        }
+    }
+
+    #[test]
+    fn holds_straight_and_level() {
+        let mut plane = Aircraft::flying_high();
+        let trim = solve(&mut plane, &Target::new(50.0, 0.0, 0.0)).unwrap();
+        assert!(trim.throttle > 0.0 && trim.throttle < 1.0);
+        assert!(trim.alpha > 0.0 && trim.alpha < 10.0);
+        // the tab takes the elevator, the stick is left in the middle
+        assert!(plane.controls.elevator.abs() < 1e-9);
+        assert!((rad_to_deg(plane.controls.elevator_angle()) - trim.elevator).abs() < 1e-6);
+
+        let height = plane.state.position.z;
+        fly(&mut plane, 10.0);
+        assert!((plane.state.airspeed() - 50.0).abs() < 0.5);
+        assert!((plane.state.position.z - height).abs() < 5.0);
+        assert!(plane.state.angular_rate.magnitude() < 0.01);
+    }
+
+    #[test]
+    fn climbs_and_turns() {
+        let mut plane = Aircraft::flying_high();
+        solve(&mut plane, &Target::new(40.0, 3.0, 0.0)).unwrap();
+        let height = plane.state.position.z;
+        fly(&mut plane, 10.0);
+        assert!((plane.state.velocity.z - 3.0).abs() < 0.3);
+        assert!((plane.state.position.z - height - 30.0).abs() < 3.0);
+
+        // a 30 degree bank to the left goes round at g tan(bank) / v
+        let mut plane = Aircraft::flying_high();
+        let trim = solve(&mut plane, &Target::new(45.0, 0.0, 30.0)).unwrap();
+        assert!(trim.sideslip.abs() < 2.0);
+        let (height, track) = (plane.state.position.z, plane.state.track());
+        fly(&mut plane, 10.0);
+        let turned = (track - plane.state.track()).rem_euclid(360.0);
+        let expected = rad_to_deg(9.81 * deg_to_rad(30.0).tan() / 45.0) * 10.0;
+        assert!((turned - expected).abs() < 5.0);
+        assert!((plane.state.position.z - height).abs() < 5.0);
+    }
+
+    #[test]
+    fn says_when_it_cant() {
+        let mut plane = Aircraft::flying_high();
+        let before = plane.state.velocity;
+        assert!(solve(&mut plane, &Target::new(20.0, 0.0, 0.0)).unwrap_err().contains("stalled"));
+        // 2g at 35 m/s is past the accelerated stall speed
+        assert!(solve(&mut plane, &Target::new(35.0, 0.0, 60.0)).unwrap_err().contains("stalled"));
+        assert!(solve(&mut plane, &Target::new(35.0, 15.0, 0.0)).unwrap_err().contains("power"));
+        assert!(solve(&mut plane, &Target::new(40.0, 0.0, 95.0)).is_err());
+        // and it leaves the plane alone
+        assert!(plane.state.velocity == before);
+    }
+}
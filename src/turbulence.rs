@@ -0,0 +1,293 @@
+use crate::common_math::Vec3;
+
+// the dryden numbers are all in feet in the spec, so theyre worked out in feet and turned back into metres
+const FEET: f64 = 0.3048;
+const LOW_ALTITUDE: f64 = 1000.0 * FEET; // below this its the low altitude model
+const HIGH_ALTITUDE: f64 = 2000.0 * FEET; // above this its the medium/high altitude one, and it blends in between
+const HIGH_SCALE_LENGTH: f64 = 1750.0 * FEET;
+const LOWEST: f64 = 10.0 * FEET; // the scale lengths go to nothing at the ground, this stops the filters blowing up
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Intensity {
+    None,
+    Light,
+    Moderate,
+    Severe,
+}
+
+impl Intensity {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Intensity::None => "NONE",
+            Intensity::Light => "LIGHT",
+            Intensity::Moderate => "MODERATE",
+            Intensity::Severe => "SEVERE",
+        }
+    }
+
+    pub fn next(&self) -> Intensity {
+        match self {
+            Intensity::None => Intensity::Light,
+            Intensity::Light => Intensity::Moderate,
+            Intensity::Moderate => Intensity::Severe,
+            Intensity::Severe => Intensity::None,
+        }
+    }
+
+    fn wind_at_20ft(&self) -> f64 {
+        // m/s, the low altitude model is scaled off the wind speed at 20ft. 15, 30 and 45 knots
+        match self {
+            Intensity::None => 0.0,
+            Intensity::Light => 7.7,
+            Intensity::Moderate => 15.4,
+            Intensity::Severe => 23.2,
+        }
+    }
+
+    fn high_altitude_sigma(&self) -> f64 {
+        // m/s, read off the probability of exceedance chart at 2000ft (1 in 100, 1 in 1000 and 1 in 100000)
+        // it drops off slowly higher up but that gets ignored
+        match self {
+            Intensity::None => 0.0,
+            Intensity::Light => 1.0,
+            Intensity::Moderate => 2.0,
+            Intensity::Severe => 4.8,
+        }
+    }
+}
+
+// splitmix64, nothing fancy but its the same numbers every time for the same seed
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Random {
+        Random { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    pub fn uniform(&mut self) -> f64 {
+        // 0 to 1, never quite 0 so the log below is fine
+        ((self.next_u64() >> 11) as f64 + 0.5) / (1u64 << 53) as f64
+    }
+
+    pub fn gaussian(&mut self) -> f64 {
+        // box muller, mean 0 and standard deviation 1
+        (-2.0 * self.uniform().ln()).sqrt() * (2.0 * std::f64::consts::PI * self.uniform()).cos()
+    }
+}
+
+// a discrete 1-cosine gust, it ramps up to the peak halfway through and back down again
+#[derive(Clone, Copy, Debug)]
+pub struct Gust {
+    pub peak: Vec3, // m/s, body coords
+    pub length: f64, // m of air flown through from start to finish
+    travelled: f64,
+}
+
+impl Gust {
+    fn velocity(&self) -> Vec3 {
+        let fraction = self.travelled / self.length;
+        self.peak * (0.5 * (1.0 - (2.0 * std::f64::consts::PI * fraction).cos()))
+    }
+}
+
+/*
+dryden turbulence as in MIL-F-8785C. white noise goes through shaping filters so it comes out with the right
+spectrum, the intensities and scale lengths change with height. u is along the body, v out to the left and w up.
+the angular bits come from the gust changing along the plane, the wing and the tail see different air,
+and they get worked out as how fast the air is turning like a solid body, which the aero damping then feels
+ */
+pub struct Turbulence {
+    pub intensity: Intensity,
+    pub velocity: Vec3, // m/s of the air, body coords, including any gust
+    pub rate: Vec3, // rad/s the air is turning at, body coords
+    pub gust: Option<Gust>,
+    random: Random,
+    u: f64, // the filter states, all for unit variance noise
+    v: [f64; 2],
+    w: [f64; 2],
+    p: f64,
+    q: f64, // lagged copies of the v and w gusts, the rates come from how far they are behind
+    r: f64,
+}
+
+impl Turbulence {
+    pub fn new(seed: u64) -> Turbulence {
+        Turbulence {
+            intensity: Intensity::None,
+            velocity: Vec3::new(0.0, 0.0, 0.0),
+            rate: Vec3::new(0.0, 0.0, 0.0),
+            gust: None,
+            random: Random::new(seed),
+            u: 0.0,
+            v: [0.0; 2],
+            w: [0.0; 2],
+            p: 0.0,
+            q: 0.0,
+            r: 0.0,
+        }
+    }
+
+    pub fn gust(&mut self, peak: Vec3, length: f64) {
+        self.gust = Some(Gust { peak, length, travelled: 0.0 });
+    }
+
+    pub fn scales(&self, altitude: f64) -> (Vec3, Vec3) {
+        // the (u, v, w) scale lengths in m and standard deviations in m/s at this height
+        let low = |altitude: f64| {
+            let feet = altitude.max(LOWEST) / FEET;
+            let factor = 0.177 + 0.000823 * feet;
+            let sigma_w = 0.1 * self.intensity.wind_at_20ft();
+            let sideways = feet / factor.powf(1.2) * FEET;
+            let sigma = sigma_w / factor.powf(0.4);
+            (Vec3::new(sideways, sideways, feet * FEET), Vec3::new(sigma, sigma, sigma_w))
+        };
+        let sigma = self.intensity.high_altitude_sigma();
+        let high = (Vec3::new(HIGH_SCALE_LENGTH, HIGH_SCALE_LENGTH, HIGH_SCALE_LENGTH), Vec3::new(sigma, sigma, sigma));
+        if altitude <= LOW_ALTITUDE {
+            low(altitude)
+        } else if altitude >= HIGH_ALTITUDE {
+            high
+        } else {
+            let fraction = (altitude - LOW_ALTITUDE) / (HIGH_ALTITUDE - LOW_ALTITUDE);
+            let (lengths, sigmas) = low(LOW_ALTITUDE);
+            (lengths * (1.0 - fraction) + &(high.0 * fraction), sigmas * (1.0 - fraction) + &(high.1 * fraction))
+        }
+    }
+
+    pub fn step(&mut self, dt: f64, airspeed: f64, altitude: f64, span: f64) {
+        let speed = airspeed.max(1.0);
+        let (lengths, sigmas) = self.scales(altitude);
+
+        // white noise for this step, scaled so the filters come out right whatever dt is
+        let mut noise = || self.random.gaussian() / dt.sqrt();
+        let (noise_u, noise_v, noise_w, noise_p) = (noise(), noise(), noise(), noise());
+        let lag = |state: &mut f64, input: f64, time_constant: f64| *state += dt / time_constant.max(dt) * (input - *state);
+
+        // u is a first order filter, v and w are second order with a zero in them
+        lag(&mut self.u, noise_u, lengths.x / speed);
+        let u = sigmas.x * (2.0 * lengths.x / speed).sqrt() * self.u;
+        lag(&mut self.v[0], noise_v, lengths.y / speed);
+        let first = self.v[0];
+        lag(&mut self.v[1], first, lengths.y / speed);
+        let v = sigmas.y * (lengths.y / speed).sqrt() * (self.v[1] + 3f64.sqrt() * (self.v[0] - self.v[1]));
+        lag(&mut self.w[0], noise_w, lengths.z / speed);
+        let first = self.w[0];
+        lag(&mut self.w[1], first, lengths.z / speed);
+        let w = sigmas.z * (lengths.z / speed).sqrt() * (self.w[1] + 3f64.sqrt() * (self.w[0] - self.w[1]));
+
+        // roll is its own noise, spread across the span. pitch and yaw are the gust changing along the plane
+        let roll_lag = 4.0 * span / (std::f64::consts::PI * speed);
+        lag(&mut self.p, noise_p, roll_lag);
+        let p = sigmas.z * (0.8 * std::f64::consts::PI / (lengths.z * speed)).sqrt()
+            * (std::f64::consts::PI * lengths.z / (4.0 * span)).powf(1.0 / 6.0) * self.p;
+        let pitch_lag = 4.0 * span / (std::f64::consts::PI * speed);
+        lag(&mut self.q, w, pitch_lag);
+        let yaw_lag = 3.0 * span / (std::f64::consts::PI * speed);
+        lag(&mut self.r, v, yaw_lag);
+        // more updraft further forward is the air turning nose up, which is negative about body y
+        let q = -(w - self.q) / (pitch_lag * speed);
+        let r = (v - self.r) / (yaw_lag * speed);
+
+        let mut gust_velocity = Vec3::new(0.0, 0.0, 0.0);
+        if let Some(gust) = &mut self.gust {
+            gust.travelled += speed * dt;
+            gust_velocity = gust.velocity();
+            if gust.travelled >= gust.length {
+                self.gust = None;
+            }
+        }
+        self.velocity = Vec3::new(u, v, w) + &gust_velocity;
+        self.rate = Vec3::new(p, q, r);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn standard_deviation(samples: &[f64]) -> f64 {
+        let mean = samples.iter().sum::<f64>() / samples.len() as f64;
+        (samples.iter().map(|sample| (sample - mean).powf(2.0)).sum::<f64>() / samples.len() as f64).sqrt()
+    }
+
+    fn fly(turbulence: &mut Turbulence, altitude: f64, seconds: f64) -> Vec<Vec3> {
+        let dt = 0.005;
+        (0..(seconds / dt) as usize).map(|_| {
+            turbulence.step(dt, 50.0, altitude, 11.0);
+            turbulence.velocity
+        }).collect()
+    }
+
+    #[test]
+    fn same_seed_same_bumps() {
+        let mut first = Turbulence::new(7);
+        let mut second = Turbulence::new(7);
+        let mut other = Turbulence::new(8);
+        for turbulence in [&mut first, &mut second, &mut other] {
+            turbulence.intensity = Intensity::Moderate;
+        }
+        let (a, b, c) = (fly(&mut first, 500.0, 10.0), fly(&mut second, 500.0, 10.0), fly(&mut other, 500.0, 10.0));
+        assert!(a.iter().zip(&b).all(|(a, b)| a.x == b.x && a.z == b.z));
+        assert!(a.iter().zip(&c).any(|(a, c)| a.x != c.x));
+
+        // and no turbulence is no turbulence
+        let mut calm = Turbulence::new(7);
+        assert!(fly(&mut calm, 500.0, 10.0).iter().all(|velocity| velocity.magnitude() == 0.0));
+    }
+
+    #[test]
+    fn comes_out_as_strong_as_the_spec() {
+        // over a long enough run the spread should match the sigma it was asked for
+        let mut turbulence = Turbulence::new(1);
+        turbulence.intensity = Intensity::Severe;
+        let samples = fly(&mut turbulence, 2000.0, 600.0);
+        let (_, sigmas) = turbulence.scales(2000.0);
+        let u: Vec<f64> = samples.iter().map(|velocity| velocity.x).collect();
+        let w: Vec<f64> = samples.iter().map(|velocity| velocity.z).collect();
+        assert!((standard_deviation(&u) / sigmas.x - 1.0).abs() < 0.25);
+        assert!((standard_deviation(&w) / sigmas.z - 1.0).abs() < 0.25);
+
+        let mut light = Turbulence::new(1);
+        light.intensity = Intensity::Light;
+        let light_w: Vec<f64> = fly(&mut light, 2000.0, 600.0).iter().map(|velocity| velocity.z).collect();
+        assert!(standard_deviation(&light_w) < 0.5 * standard_deviation(&w));
+    }
+
+    #[test]
+    fn eddies_get_bigger_higher_up() {
+        let mut turbulence = Turbulence::new(1);
+        turbulence.intensity = Intensity::Moderate;
+        let (low, low_sigma) = turbulence.scales(30.0);
+        let (middle, _) = turbulence.scales(450.0);
+        let (high, _) = turbulence.scales(3000.0);
+        assert!(low.z < middle.z && middle.z < high.z);
+        // the vertical ones are squashed near the ground, the horizontal ones are stronger
+        assert!(low.z < low.x);
+        assert!(low_sigma.x > low_sigma.z);
+        assert!((high.x - 533.4).abs() < 0.1);
+    }
+
+    #[test]
+    fn one_minus_cosine_gust() {
+        let mut turbulence = Turbulence::new(1);
+        turbulence.gust(Vec3::new(0.0, 0.0, 5.0), 100.0);
+        // at 50m/s it takes 2 seconds to get through, peaking at 1
+        let samples = fly(&mut turbulence, 500.0, 3.0);
+        let peak = samples.iter().map(|velocity| velocity.z).fold(0.0, f64::max);
+        assert!((peak - 5.0).abs() < 0.01);
+        assert!((samples[199].z - 5.0).abs() < 0.01);
+        assert!(samples[50].z > 0.0 && samples[50].z < samples[100].z);
+        assert!(turbulence.gust.is_none());
+        assert_eq!(samples.last().unwrap().z, 0.0);
+    }
+}
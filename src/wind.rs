@@ -0,0 +1,97 @@
+use crate::common_math::{deg_to_rad, Vec3};
+
+// surface winds get reported at 10m, below that they die off towards the ground
+const SURFACE_HEIGHT: f64 = 10.0; // m
+const BOUNDARY_LAYER_EXPONENT: f64 = 1.0 / 7.0;
+
+/*
+the world is x north, y west and z up. winds are given like a forecast, the way theyre coming from
+in compass degrees and how fast. in between layers it blends straight across, which is the shear
+ */
+#[derive(Clone, Copy, Debug)]
+pub struct WindLayer {
+    pub altitude: f64, // m
+    pub from: f64, // degrees, where its blowing from
+    pub speed: f64, // m/s
+}
+
+pub struct Wind {
+    pub layers: Vec<WindLayer>, // lowest first
+}
+
+impl WindLayer {
+    pub fn velocity(&self) -> Vec3 {
+        // which way the air is actually moving, the opposite of where its coming from
+        let from = deg_to_rad(self.from);
+        Vec3::new(-self.speed * from.cos(), self.speed * from.sin(), 0.0)
+    }
+}
+
+impl Wind {
+    pub fn calm() -> Wind {
+        Wind {
+            layers: Vec::new(),
+        }
+    }
+
+    pub fn new(mut layers: Vec<WindLayer>) -> Wind {
+        layers.sort_by(|a, b| a.altitude.total_cmp(&b.altitude));
+        Wind { layers }
+    }
+
+    #[allow(dead_code)]
+    pub fn steady(from: f64, speed: f64) -> Wind {
+        // the same at every height, apart from near the ground
+        Wind::new(vec![WindLayer { altitude: SURFACE_HEIGHT, from, speed }])
+    }
+
+    pub fn at(&self, altitude: f64) -> Vec3 {
+        // the velocity of the air in world coords
+        let (first, last) = match (self.layers.first(), self.layers.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return Vec3::new(0.0, 0.0, 0.0),
+        };
+        if altitude <= first.altitude {
+            // friction with the ground slows it down, its a power law in the bottom bit
+            let height = first.altitude.min(SURFACE_HEIGHT);
+            let scale = (altitude.max(0.0) / height).powf(BOUNDARY_LAYER_EXPONENT).min(1.0);
+            return first.velocity() * scale;
+        }
+        for pair in self.layers.windows(2) {
+            let (below, above) = (&pair[0], &pair[1]);
+            if altitude <= above.altitude {
+                let fraction = (altitude - below.altitude) / (above.altitude - below.altitude);
+                return below.velocity() * (1.0 - fraction) + &(above.velocity() * fraction);
+            }
+        }
+        last.velocity()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn blows_the_right_way() {
+        // a northerly blows south, an easterly blows west (+y)
+        let north = Wind::steady(0.0, 10.0).at(100.0);
+        assert!((north.x + 10.0).abs() < 1e-9 && north.y.abs() < 1e-9);
+        let east = Wind::steady(90.0, 10.0).at(100.0);
+        assert!((east.y - 10.0).abs() < 1e-9 && east.x.abs() < 1e-9);
+        assert_eq!(Wind::calm().at(100.0).magnitude(), 0.0);
+    }
+
+    #[test]
+    fn shears_between_layers() {
+        let wind = Wind::new(vec![
+            WindLayer { altitude: 1000.0, from: 270.0, speed: 20.0 },
+            WindLayer { altitude: 10.0, from: 270.0, speed: 10.0 },
+        ]);
+        assert!((wind.at(505.0).magnitude() - 15.0).abs() < 1e-9);
+        assert!((wind.at(3000.0).magnitude() - 20.0).abs() < 1e-9);
+        // and it drops off close to the ground
+        assert!(wind.at(1.0).magnitude() < 0.8 * wind.at(10.0).magnitude());
+        assert_eq!(wind.at(0.0).magnitude(), 0.0);
+    }
+}
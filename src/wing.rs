@@ -0,0 +1,261 @@
+use serde::Deserialize;
+
+use crate::common_math::{deg_to_rad, interpolate, rad_to_deg, Vec3};
+use crate::definition::AircraftDefinition;
+use crate::flaps::FlapIncrements;
+
+const STALL_HORN_MARGIN: f64 = 4.0; // degrees before the stall, about 5 to 10 knots on a 172
+const PANEL_ARM: f64 = 0.5; // where the middle of each half of the wing is, as a fraction of the half span
+
+/*
+close to the ground the wingtip vortices cant get all the way round and there's less downwash, so the wing
+acts like it has a bigger aspect ratio. mccormicks fit for how much of the induced drag is left, and the lift
+slope goes up to match the stretched wing. it's gone by about a span up
+ */
+pub struct GroundEffect {
+    pub lift: f64, // multiplies the lift coefficient
+    pub induced_drag: f64, // multiplies the induced drag
+}
+
+impl GroundEffect {
+    #[allow(dead_code)]
+    pub fn none() -> GroundEffect {
+        GroundEffect { lift: 1.0, induced_drag: 1.0 }
+    }
+
+    pub fn at(height: f64, span: f64, area: f64) -> GroundEffect {
+        // height of the wing above the ground
+        let squashed = (16.0 * height.max(0.0) / span).powf(2.0);
+        let induced_drag = squashed / (1.0 + squashed);
+        if induced_drag < 1e-3 {
+            return GroundEffect { lift: 1.0, induced_drag };
+        }
+        let lift_slope = |aspect_ratio: f64| aspect_ratio / (aspect_ratio + 2.0);
+        let aspect_ratio = span.powf(2.0) / area;
+        GroundEffect {
+            lift: lift_slope(aspect_ratio / induced_drag) / lift_slope(aspect_ratio),
+            induced_drag,
+        }
+    }
+}
+
+// the wing lets go at the critical alpha but doesnt come back until youre well below it
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct WingDefinition {
+    pub attached_lift: Vec<(f64, f64)>, // lift coefficient against alpha in degrees while the flow is stuck to the wing
+    pub separated_lift: Vec<(f64, f64)>, // and once its come off, it drops a lot and then turns into a flat plate
+    pub max_lift: f64,
+    pub zero_lift_drag: f64,
+    pub induced_drag: f64,
+    pub critical_alpha: f64,
+    pub critical_alpha_negative: f64,
+    pub reattach_alpha: f64,
+    pub reattach_alpha_negative: f64,
+    #[serde(default)]
+    pub dihedral: f64, // degrees, gives the wing into the sideslip a bit more alpha
+}
+
+// the lift and drag curves dont care about the hysteresis, whoever asks says whether its stalled
+impl WingDefinition {
+    pub fn lift(&self, alpha: f64, flap: &FlapIncrements, ground: &GroundEffect, stalled: bool) -> f64 {
+        let alpha = rad_to_deg(alpha);
+        if stalled {
+            interpolate(&self.separated_lift, alpha) + flap.lift
+        } else {
+            ground.lift * (interpolate(&self.attached_lift, alpha) + flap.lift).min(self.max_lift + flap.max_lift)
+        }
+    }
+
+    pub fn drag(&self, alpha: f64, flap: &FlapIncrements, ground: &GroundEffect, stalled: bool) -> f64 {
+        if stalled {
+            // mostly just a flat plate once its stalled
+            self.zero_lift_drag + 1.2 * alpha.sin().powf(2.0) + flap.drag
+        } else {
+            ground.induced_drag * (alpha.powf(2.0)/(std::f64::consts::PI * self.induced_drag)) + self.zero_lift_drag + flap.drag
+        }
+    }
+
+    pub fn panel_arm(&self, span: f64) -> f64 {
+        PANEL_ARM * span / 2.0
+    }
+
+    pub fn panels(&self, body_velocity: &Vec3, rate: &Vec3, span: f64) -> [(f64, f64); 2] {
+        /*
+        the alpha each half of the wing sees and its dynamic pressure over the freestream, left then right.
+        rolling, the down going wing meets the air from further underneath. yawing, the wing on the inside
+        goes slower so the same updraft is a bigger angle, and the outside one goes faster
+         */
+        let airspeed = body_velocity.magnitude();
+        if airspeed < 1.0 {
+            return [(0.0, 1.0); 2];
+        }
+        let sideslip = (-body_velocity.y / airspeed).asin();
+        let dihedral = deg_to_rad(self.dihedral).sin();
+        [1.0, -1.0].map(|side: f64| {
+            let velocity = *body_velocity + &rate.cross_product(&Vec3::new(0.0, side * self.panel_arm(span), 0.0));
+            let local = Vec3::new(velocity.x, 0.0, velocity.z);
+            // air from the right comes up under the right wing
+            let alpha = (-velocity.z).atan2(velocity.x) - side * sideslip * dihedral;
+            (alpha, (local.magnitude() / airspeed).powf(2.0))
+        })
+    }
+
+    pub fn asymmetry(&self, panels: [(f64, f64); 2], flap: &FlapIncrements, stalled: [bool; 2]) -> (f64, f64) {
+        /*
+        how much more force the left half makes than the right, as body z and x coefficients on the whole wing area.
+        whatever a straight line lift curve and the attached drag would give is taken off, thats the roll and
+        yaw damping the stability derivatives already have. while the flow is attached its next to nothing,
+        once a wing stalls it turns into the autorotation that drives a spin
+         */
+        let ground = GroundEffect::none();
+        let extra = |side: usize| {
+            let (alpha, pressure) = panels[side];
+            let lift = pressure * (self.lift(alpha, flap, &ground, stalled[side]) - self.lift(alpha, flap, &ground, false));
+            let drag = pressure * (self.drag(alpha, flap, &ground, stalled[side]) - self.drag(alpha, flap, &ground, false));
+            // lift is square to the local air and drag along it, turned into up and forwards on the body
+            (lift * alpha.cos() + drag * alpha.sin(), lift * alpha.sin() - drag * alpha.cos())
+        };
+        let (left, right) = (extra(0), extra(1));
+        ((left.0 - right.0) / 4.0, (left.1 - right.1) / 4.0)
+    }
+}
+
+pub struct Wing {
+    pub stalled: bool,
+    pub panels_stalled: [bool; 2], // the left and right halves on their own, one can let go before the other
+    definition: WingDefinition,
+}
+
+impl Wing {
+    pub fn new(definition: WingDefinition) -> Wing {
+        Wing {
+            stalled: false,
+            panels_stalled: [false; 2],
+            definition,
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn cessna() -> Wing {
+        Wing::new(AircraftDefinition::cessna().wing)
+    }
+
+    pub fn update(&mut self, alpha: f64) {
+        // alpha in radians, call once a step so the hysteresis doesnt flicker
+        self.stalled = self.still_stalled(self.stalled, alpha);
+    }
+
+    pub fn update_panels(&mut self, alphas: [f64; 2]) {
+        // same again for each half of the wing, left then right
+        self.panels_stalled = [0, 1].map(|side| self.still_stalled(self.panels_stalled[side], alphas[side]));
+    }
+
+    fn still_stalled(&self, stalled: bool, alpha: f64) -> bool {
+        let alpha = rad_to_deg(alpha);
+        let wing = &self.definition;
+        if !(wing.critical_alpha_negative..=wing.critical_alpha).contains(&alpha) {
+            true
+        } else if (wing.reattach_alpha_negative..wing.reattach_alpha).contains(&alpha) {
+            false
+        } else {
+            stalled
+        }
+    }
+
+    pub fn panels(&self, body_velocity: &Vec3, rate: &Vec3, span: f64) -> [(f64, f64); 2] {
+        self.definition.panels(body_velocity, rate, span)
+    }
+
+    pub fn stall_margin(&self, alpha: f64) -> f64 {
+        // degrees of alpha left before the wing stalls, negative once youre past it
+        self.definition.critical_alpha - rad_to_deg(alpha)
+    }
+
+    pub fn is_stalling(&self, alpha: f64) -> bool {
+        // what the stall horn listens to
+        self.stalled || self.stall_margin(alpha) < STALL_HORN_MARGIN
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::common_math::deg_to_rad;
+
+    #[test]
+    fn lift_drops_after_stall() {
+        let mut wing = Wing::cessna();
+        let clean = FlapIncrements::at(0.0);
+        wing.update(deg_to_rad(13.0));
+        let before = wing.definition.lift(deg_to_rad(13.0), &clean, &GroundEffect::none(), wing.stalled);
+        let drag_before = wing.definition.drag(deg_to_rad(13.0), &clean, &GroundEffect::none(), wing.stalled);
+        wing.update(deg_to_rad(16.0));
+        assert!(wing.stalled);
+        let after = wing.definition.lift(deg_to_rad(16.0), &clean, &GroundEffect::none(), wing.stalled);
+        assert!(after < before - 0.2);
+        assert!(wing.definition.drag(deg_to_rad(16.0), &clean, &GroundEffect::none(), wing.stalled) > drag_before);
+    }
+
+    #[test]
+    fn hysteresis() {
+        let mut wing = Wing::cessna();
+        let clean = FlapIncrements::at(0.0);
+        wing.update(deg_to_rad(15.0));
+        // coming back down through 12 degrees its still stalled
+        wing.update(deg_to_rad(12.0));
+        assert!(wing.stalled);
+        let stalled_lift = wing.definition.lift(deg_to_rad(12.0), &clean, &GroundEffect::none(), wing.stalled);
+        wing.update(deg_to_rad(9.0));
+        assert!(!wing.stalled);
+        wing.update(deg_to_rad(12.0));
+        assert!(!wing.stalled);
+        assert!(wing.definition.lift(deg_to_rad(12.0), &clean, &GroundEffect::none(), wing.stalled) > stalled_lift);
+    }
+
+    #[test]
+    fn stall_horn() {
+        let wing = Wing::cessna();
+        assert!(!wing.is_stalling(deg_to_rad(5.0)));
+        assert!(wing.is_stalling(deg_to_rad(11.0)));
+        assert!((wing.stall_margin(deg_to_rad(11.0)) - 3.0).abs() < 1e-10);
+    }
+
+    #[test]
+    fn floats_near_the_ground() {
+        let wing = Wing::cessna();
+        let clean = FlapIncrements::at(0.0);
+        let alpha = deg_to_rad(8.0);
+        let (span, area) = (11.0, 16.17);
+        // a quarter of a span up, theres noticeably more lift and less drag
+        let low = GroundEffect::at(0.25 * span, span, area);
+        assert!(wing.definition.lift(alpha, &clean, &low, false) > 1.01 * wing.definition.lift(alpha, &clean, &GroundEffect::none(), false));
+        assert!(wing.definition.drag(alpha, &clean, &low, false) < wing.definition.drag(alpha, &clean, &GroundEffect::none(), false));
+        // it gets stronger the lower you go, and is all gone a span up
+        let lower = GroundEffect::at(0.1 * span, span, area);
+        assert!(lower.lift > low.lift && lower.induced_drag < low.induced_drag);
+        let high = GroundEffect::at(span, span, area);
+        assert!((high.induced_drag - 1.0).abs() < 0.01 && (high.lift - 1.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn drops_the_wing_going_down() {
+        let mut wing = Wing::cessna();
+        let clean = FlapIncrements::at(0.0);
+        let velocity = Vec3::new(35.0, 0.0, -8.0);
+        // rolling left the left wing goes down into the air, yawing left the right wing goes faster
+        let [left, right] = wing.panels(&velocity, &Vec3::new(-0.5, 0.0, 0.0), 11.0);
+        assert!(left.0 > right.0);
+        let [left, right] = wing.panels(&velocity, &Vec3::new(0.0, 0.0, 0.5), 11.0);
+        assert!(right.1 > left.1);
+
+        // with the flow still on theres nothing extra, once the left side lets go it drops
+        let panels = wing.panels(&velocity, &Vec3::new(-0.5, 0.0, 0.0), 11.0);
+        assert_eq!(wing.definition.asymmetry(panels, &clean, wing.panels_stalled), (0.0, 0.0));
+        wing.update_panels([deg_to_rad(16.0), deg_to_rad(12.0)]);
+        assert_eq!(wing.panels_stalled, [true, false]);
+        let (up, forwards) = wing.definition.asymmetry(panels, &clean, wing.panels_stalled);
+        assert!(up < 0.0);
+        assert!(forwards < 0.0);
+    }
+}